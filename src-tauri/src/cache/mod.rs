@@ -105,6 +105,11 @@ impl<K: Hash + Eq, V> Cache<K, V> {
         let mut cache = self.cache.lock();
         cache.pop(key);
     }
+
+    pub fn clear(&self) {
+        let mut cache = self.cache.lock();
+        cache.clear();
+    }
 }
 
 impl<T: Clone> TimedCacheEntry<T> {
@@ -1,11 +1,15 @@
-use tauri::command;
+use tauri::{command, Emitter};
 use tauri_plugin_dialog::DialogExt;
 use crate::games::manager::GameManager;
-use crate::games::Game;
+use crate::games::{Game, UpdateCheck};
+use crate::history::{HistoryPage, HistoryQuery, HistoryService};
+use crate::queue::{QueueEntry, QueueManager, QueueOrigin};
+use crate::scheduler::{Scheduler, SchedulerStatus};
 use crate::settings::manager::SettingsManager;
-use crate::settings::Settings;
+use crate::settings::{Settings, SettingsPatch};
 use crate::registry::{steam::SteamRegistry, epic::EpicRegistry, RegistryReader};
-use crate::error::Result;
+use crate::steam::BuildComparison;
+use crate::error::{CommandError, Result};
 use crate::{log_debug, log_error};
 use serde::Serialize;
 
@@ -30,22 +34,135 @@ pub async fn refresh_games_list(game_manager: tauri::State<'_, GameManager>) ->
 }
 
 #[command]
-pub async fn update_game(game_id: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<(), String> {
+pub async fn update_game(game_id: String, update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<(), CommandError> {
     log_debug!("Command: update_game, game_id: {}", game_id);
-    game_manager.update_game(&game_id).await
+    // Через очередь, чтобы не превышать лимит одновременных обновлений;
+    // ход обновления приходит в событиях update-progress
+    update_queue.enqueue(&game_id, 0, QueueOrigin::Manual).await
+        .map(|_| ())
         .map_err(|e| {
             log_error!("Failed to update game {}: {}", game_id, e);
-            e.to_string()
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn cancel_update(game_id: String, update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<(), CommandError> {
+    log_debug!("Command: cancel_update, game_id: {}", game_id);
+    update_queue.cancel(&game_id).await
+        .map_err(|e| {
+            log_error!("Failed to cancel update of game {}: {}", game_id, e);
+            CommandError::from(e)
         })
 }
 
 #[command]
-pub async fn check_game_updates(game_id: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<bool, String> {
+pub async fn pause_update(game_id: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<(), CommandError> {
+    log_debug!("Command: pause_update, game_id: {}", game_id);
+    game_manager.pause_update(&game_id)
+        .map_err(|e| {
+            log_error!("Failed to pause update of game {}: {}", game_id, e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn resume_update(game_id: String, update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<Vec<QueueEntry>, CommandError> {
+    log_debug!("Command: resume_update, game_id: {}", game_id);
+    update_queue.resume(&game_id).await
+        .map_err(|e| {
+            log_error!("Failed to resume update of game {}: {}", game_id, e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn close_game(game_id: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<(), CommandError> {
+    log_debug!("Command: close_game, game_id: {}", game_id);
+    game_manager.close_game(&game_id).await
+        .map_err(|e| {
+            log_error!("Failed to close game {}: {}", game_id, e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn get_update_history(query: Option<HistoryQuery>, history: tauri::State<'_, HistoryService>) -> std::result::Result<HistoryPage, CommandError> {
+    log_debug!("Command: get_update_history, query: {:?}", query);
+    history.query(&query.unwrap_or_default()).await
+        .map_err(|e| {
+            log_error!("Failed to read update history: {}", e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn enqueue_update(game_id: String, priority: Option<i64>, update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<Vec<QueueEntry>, CommandError> {
+    log_debug!("Command: enqueue_update, game_id: {}", game_id);
+    update_queue.enqueue(&game_id, priority.unwrap_or(0), QueueOrigin::Manual).await
+        .map_err(|e| {
+            log_error!("Failed to enqueue update of game {}: {}", game_id, e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn list_queue(update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<Vec<QueueEntry>, CommandError> {
+    log_debug!("Command: list_queue");
+    update_queue.list().await
+        .map_err(|e| {
+            log_error!("Failed to list update queue: {}", e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn reorder_queue(game_ids: Vec<String>, update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<Vec<QueueEntry>, CommandError> {
+    log_debug!("Command: reorder_queue, game_ids: {:?}", game_ids);
+    update_queue.reorder(&game_ids).await
+        .map_err(|e| {
+            log_error!("Failed to reorder update queue: {}", e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn remove_from_queue(game_id: String, update_queue: tauri::State<'_, QueueManager>) -> std::result::Result<Vec<QueueEntry>, CommandError> {
+    log_debug!("Command: remove_from_queue, game_id: {}", game_id);
+    update_queue.remove(&game_id).await
+        .map_err(|e| {
+            log_error!("Failed to remove game {} from update queue: {}", game_id, e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn check_game_updates(game_id: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<UpdateCheck, CommandError> {
     log_debug!("Command: check_game_updates, game_id: {}", game_id);
     game_manager.check_game_updates(&game_id).await
         .map_err(|e| {
             log_error!("Failed to check updates for game {}: {}", game_id, e);
-            e.to_string()
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn compare_game_builds(game_id: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<BuildComparison, CommandError> {
+    log_debug!("Command: compare_game_builds, game_id: {}", game_id);
+    game_manager.compare_game_builds(&game_id).await
+        .map_err(|e| {
+            log_error!("Failed to compare builds for game {}: {}", game_id, e);
+            CommandError::from(e)
+        })
+}
+
+#[command]
+pub async fn submit_steam_guard_code(code: String, game_manager: tauri::State<'_, GameManager>) -> std::result::Result<(), CommandError> {
+    log_debug!("Command: submit_steam_guard_code");
+    game_manager.submit_steam_guard_code(&code)
+        .map_err(|e| {
+            log_error!("Failed to submit Steam Guard code: {}", e);
+            CommandError::from(e)
         })
 }
 
@@ -60,13 +177,63 @@ pub async fn get_settings(settings_manager: tauri::State<'_, SettingsManager>) -
 }
 
 #[command]
-pub async fn save_settings(settings: Settings, settings_manager: tauri::State<'_, SettingsManager>) -> std::result::Result<(), String> {
+pub async fn save_settings(
+    settings: Settings,
+    settings_manager: tauri::State<'_, SettingsManager>,
+    game_manager: tauri::State<'_, GameManager>,
+    scheduler: tauri::State<'_, Scheduler>,
+) -> std::result::Result<(), CommandError> {
     log_debug!("Command: save_settings");
-    settings_manager.save(&settings).await
+    let saved = settings_manager.save(&settings).await
         .map_err(|e| {
             log_error!("Failed to save settings: {}", e);
-            e.to_string()
-        })
+            CommandError::from(e)
+        })?;
+    game_manager.apply_settings(&settings_manager.with_password(saved));
+    scheduler.apply_settings(&settings);
+    Ok(())
+}
+
+#[command]
+pub async fn update_settings(
+    update: SettingsPatch,
+    app: tauri::AppHandle,
+    settings_manager: tauri::State<'_, SettingsManager>,
+    game_manager: tauri::State<'_, GameManager>,
+    scheduler: tauri::State<'_, Scheduler>,
+) -> std::result::Result<Settings, CommandError> {
+    let updates = update.into_updates();
+    // Значения не пишем в журнал: среди них может быть пароль
+    let keys: Vec<_> = updates.iter().map(|u| u.key.as_str()).collect();
+    log_debug!("Command: update_settings, keys: {:?}", keys);
+
+    let (settings, changes) = settings_manager.update(&updates).await
+        .map_err(|e| {
+            log_error!("Failed to update settings: {}", e);
+            CommandError::from(e)
+        })?;
+
+    if !changes.is_empty() {
+        game_manager.apply_settings(&settings_manager.with_password(settings.clone()));
+        scheduler.apply_settings(&settings);
+        if let Err(e) = app.emit("settings-changed", &changes) {
+            log_error!("Failed to emit settings-changed: {}", e);
+        }
+    }
+    Ok(settings)
+}
+
+#[command]
+pub async fn get_scheduler_status(scheduler: tauri::State<'_, Scheduler>) -> std::result::Result<SchedulerStatus, String> {
+    log_debug!("Command: get_scheduler_status");
+    Ok(scheduler.status())
+}
+
+#[command]
+pub async fn check_updates_now(scheduler: tauri::State<'_, Scheduler>) -> std::result::Result<(), String> {
+    log_debug!("Command: check_updates_now");
+    scheduler.check_now();
+    Ok(())
 }
 
 #[command]
@@ -1,3 +1,5 @@
+use serde::Serialize;
+use std::fmt;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -28,16 +30,165 @@ pub enum Error {
     #[error("Update failed: {0}")]
     UpdateFailed(String),
 
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("SteamCmd failure ({kind}): {message}")]
+    SteamCmd {
+        kind: SteamCmdFailure,
+        message: String,
+    },
+
     #[error("Logging error: {0}")]
     LoggingError(String),
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("Invalid settings: {}", describe_fields(.0))]
+    Validation(Vec<FieldError>),
+
+    #[error("Secret store error: {0}")]
+    SecretStore(String),
+
     #[error("Other error: {0}")]
     Other(String),
 }
 
+// Ошибка в конкретном поле настроек; field - путь через точку, например retry.jitter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+fn describe_fields(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Ошибка команды для фронтенда. Для неверных настроек fields перечисляет
+// поля, которые нужно исправить; для отказов steamcmd kind, retryable и hint
+// позволяют показать причину и предложить повтор
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandError {
+    pub message: String,
+    pub fields: Vec<FieldError>,
+    pub kind: Option<SteamCmdFailure>,
+    pub retryable: bool,
+    pub hint: Option<&'static str>,
+}
+
+impl From<Error> for CommandError {
+    fn from(err: Error) -> Self {
+        let message = err.to_string();
+        let kind = err.kind();
+        let retryable = err.is_retryable();
+        let hint = err.hint();
+        let fields = match err {
+            Error::Validation(fields) => fields,
+            _ => Vec::new(),
+        };
+        Self { message, fields, kind, retryable, hint }
+    }
+}
+
+// Известные причины отказа steamcmd
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SteamCmdFailure {
+    NoSubscription,
+    DiskWriteFailure,
+    NotEnoughDiskSpace,
+    InvalidPlatform,
+    RateLimitExceeded,
+    InvalidPassword,
+    TwoFactorMismatch,
+    Timeout,
+    ConnectionLost,
+    // "Error! App 'X' state is 0x602 after update job."
+    UnexpectedState(u32),
+}
+
+impl SteamCmdFailure {
+    // Имеет ли смысл повторять операцию без действий пользователя
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimitExceeded | Self::Timeout | Self::ConnectionLost | Self::UnexpectedState(_)
+        )
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::NoSubscription => "Игра не привязана к аккаунту Steam. Войдите в аккаунт, которому она принадлежит",
+            Self::DiskWriteFailure => "Не удалось записать файлы игры. Проверьте права доступа к папке библиотеки и состояние диска",
+            Self::NotEnoughDiskSpace => "Недостаточно места на диске. Освободите место и повторите обновление",
+            Self::InvalidPlatform => "Игра недоступна для этой платформы",
+            Self::RateLimitExceeded => "Steam временно ограничил число входов. Обновление будет повторено позже",
+            Self::InvalidPassword => "Неверный логин или пароль Steam. Проверьте учетные данные в настройках",
+            Self::TwoFactorMismatch => "Неверный код Steam Guard. Введите новый код",
+            Self::Timeout => "SteamCMD не отвечает. Обновление будет повторено позже",
+            Self::ConnectionLost => "Потеряно соединение с серверами Steam. Обновление будет повторено позже",
+            Self::UnexpectedState(_) => "Steam не завершил обновление. Обновление будет повторено позже",
+        }
+    }
+}
+
+impl fmt::Display for SteamCmdFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSubscription => write!(f, "no subscription"),
+            Self::DiskWriteFailure => write!(f, "disk write failure"),
+            Self::NotEnoughDiskSpace => write!(f, "not enough disk space"),
+            Self::InvalidPlatform => write!(f, "invalid platform"),
+            Self::RateLimitExceeded => write!(f, "rate limit exceeded"),
+            Self::InvalidPassword => write!(f, "invalid password"),
+            Self::TwoFactorMismatch => write!(f, "two-factor code mismatch"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::ConnectionLost => write!(f, "connection lost"),
+            Self::UnexpectedState(state) => write!(f, "unexpected state 0x{:x}", state),
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> Option<SteamCmdFailure> {
+        match self {
+            Error::SteamCmd { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::SteamCmd { kind, .. } => kind.is_retryable(),
+            _ => false,
+        }
+    }
+
+    // Текст для пользователя, если причина известна
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::SteamCmd { kind, .. } => Some(kind.hint()),
+            Error::SteamCmdNotFound => Some("SteamCMD не найден. Укажите путь к нему в настройках"),
+            _ => None,
+        }
+    }
+}
+
 impl From<sqlx::Error> for Error {
     fn from(err: sqlx::Error) -> Self {
         Error::Database(err.to_string())
@@ -55,3 +206,32 @@ impl From<tauri::Error> for Error {
         Error::Other(err.to_string())
     }
 }
+
+impl serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::ParseError(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_error_carries_steamcmd_failure() {
+        let error = CommandError::from(Error::SteamCmd {
+            kind: SteamCmdFailure::RateLimitExceeded,
+            message: "FAILED (Rate Limit Exceeded)".to_string(),
+        });
+        assert_eq!(error.kind, Some(SteamCmdFailure::RateLimitExceeded));
+        assert!(error.retryable);
+        assert_eq!(error.hint, Some(SteamCmdFailure::RateLimitExceeded.hint()));
+        assert!(error.fields.is_empty());
+
+        let error = CommandError::from(Error::UpdateFailed("exit code 1".to_string()));
+        assert_eq!(error.kind, None);
+        assert!(!error.retryable);
+        assert_eq!(error.hint, None);
+        assert_eq!(error.message, "Update failed: exit code 1");
+    }
+}
@@ -1,10 +1,15 @@
 use std::path::PathBuf;
+use std::process::Stdio;
 use tauri::{AppHandle, Emitter};
+use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use crate::error::{Result, Error};
+use crate::process;
 use crate::registry::epic::EpicRegistry;
 use crate::registry::RegistryReader;
-use super::{Game, Platform, UpdateProgress, UpdateStatus};
+use crate::logging::redact;
+use crate::steam::{CancelHandle, UpdateState};
+use super::{Attempt, Game, Platform, UpdateProgress, UpdateStatus};
 
 pub(crate) async fn get_installed_games(_epic_path: &PathBuf) -> Result<Vec<Game>> {
     let registry = EpicRegistry::new()?;
@@ -17,10 +22,17 @@ pub(crate) async fn get_installed_games(_epic_path: &PathBuf) -> Result<Vec<Game
         install_path: path,
         last_update: None,
         update_status: None,
+        steam: None,
+        running: false,
     }).collect())
 }
 
-pub(crate) async fn update_game(game_id: &str, app: &AppHandle) -> Result<()> {
+pub(crate) async fn update_game(
+    game_id: &str,
+    cancel: CancelHandle,
+    attempt: Attempt,
+    app: &AppHandle,
+) -> Result<()> {
     let registry = EpicRegistry::new()?;
     let launcher_path = registry.get_install_path()?;
     let epic_launcher = launcher_path.join("Launcher/Portal/Binaries/Win32/EpicGamesLauncher.exe");
@@ -33,53 +45,66 @@ pub(crate) async fn update_game(game_id: &str, app: &AppHandle) -> Result<()> {
     app.emit("update-progress", UpdateProgress {
         game_id: game_id.to_string(),
         progress: 0.0,
+        state: UpdateState::Starting,
         status: UpdateStatus {
             is_updating: true,
             progress: Some(0.0),
             error: None,
         },
-        message: Some("Начало обновления...".to_string()),
+        message: Some(match attempt.number {
+            1 => "Начало обновления...".to_string(),
+            n => format!("Начало обновления, попытка {}/{}...", n, attempt.max),
+        }),
+        bytes_downloaded: None,
+        bytes_total: None,
+        attempt: Some(attempt),
+        attempts: Vec::new(),
+        kind: None,
+        retryable: false,
+        hint: None,
     })?;
 
     // Запускаем обновление через Epic Games Launcher
     // Используем параметры командной строки для автоматического обновления
-    let output = Command::new(&epic_launcher)
-        .args(&[
+    let mut child = process::spawn_group(&mut Command::new(&epic_launcher))
+        .args([
             "-opengl", // Используем OpenGL рендеринг
             "-silent", // Тихий режим
             "-installupdate",
             game_id,
         ])
-        .output()
-        .await
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
         .map_err(|e| Error::ProcessError(e.to_string()))?;
 
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        app.emit("update-progress", UpdateProgress {
-            game_id: game_id.to_string(),
-            progress: 0.0,
-            status: UpdateStatus {
-                is_updating: false,
-                progress: None,
-                error: Some(error_msg.to_string()),
-            },
-            message: Some("Ошибка обновления".to_string()),
-        })?;
-        return Err(Error::UpdateFailed(error_msg.to_string()));
-    }
+    // stderr читаем параллельно, иначе лаунчер может встать на заполненном канале
+    let mut stderr_pipe = child.stderr.take();
+    let stderr = tokio::spawn(async move {
+        let mut stderr = Vec::new();
+        if let Some(pipe) = stderr_pipe.as_mut() {
+            let _ = pipe.read_to_end(&mut stderr).await;
+        }
+        stderr
+    });
 
-    // Эмитим завершение обновления
-    app.emit("update-progress", UpdateProgress {
-        game_id: game_id.to_string(),
-        progress: 100.0,
-        status: UpdateStatus {
-            is_updating: false,
-            progress: Some(100.0),
-            error: None,
-        },
-        message: Some("Обновление завершено".to_string()),
-    })?;
+    let status = tokio::select! {
+        status = child.wait() => status.map_err(|e| Error::ProcessError(e.to_string()))?,
+        _ = cancel.cancelled() => {
+            process::kill_tree(&mut child)
+                .await
+                .map_err(|e| Error::ProcessError(e.to_string()))?;
+            return Err(Error::Cancelled);
+        }
+    };
+
+    // Итоговое событие отправляет GameManager
+    if !status.success() {
+        let stderr = stderr.await.unwrap_or_default();
+        let error_msg = redact(&String::from_utf8_lossy(&stderr)).into_owned();
+        return Err(Error::UpdateFailed(error_msg));
+    }
 
     Ok(())
 }
@@ -95,7 +120,7 @@ pub(crate) async fn check_updates(game_id: &str, _app: &AppHandle) -> Result<boo
 
     // Проверяем обновления через Epic Games Launcher
     let output = Command::new(&epic_launcher)
-        .args(&[
+        .args([
             "-opengl",
             "-silent",
             "-checkforupdates",
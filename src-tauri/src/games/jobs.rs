@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use crate::error::{Error, Result};
+use crate::steam::CancelHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    // Остановлено пользователем, ожидает resume_update; очередь запустит
+    // обновление заново через start
+    Paused,
+}
+
+// Чем закончился запуск обновления
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStop {
+    Finished,
+    Paused,
+    Cancelled,
+}
+
+struct Job {
+    state: JobState,
+    cancel: CancelHandle,
+    stop: JobStop,
+}
+
+// Запущенные и приостановленные обновления по id игры
+#[derive(Default)]
+pub struct UpdateJobs {
+    jobs: Mutex<HashMap<String, Job>>,
+}
+
+impl UpdateJobs {
+    // Регистрирует запуск; handle передаётся процессу обновления
+    pub fn start(&self, game_id: &str) -> Result<CancelHandle> {
+        let mut jobs = self.jobs.lock();
+        if jobs.get(game_id).is_some_and(|job| job.state == JobState::Running) {
+            return Err(Error::UpdateFailed(format!("Update of {} is already running", game_id)));
+        }
+
+        let cancel = CancelHandle::default();
+        jobs.insert(game_id.to_string(), Job {
+            state: JobState::Running,
+            cancel: cancel.clone(),
+            stop: JobStop::Finished,
+        });
+        Ok(cancel)
+    }
+
+    // Снимает запуск с учёта; приостановленное обновление остаётся в списке
+    pub fn finish(&self, game_id: &str) -> JobStop {
+        let mut jobs = self.jobs.lock();
+        let stop = match jobs.remove(game_id) {
+            Some(job) => job.stop,
+            None => return JobStop::Finished,
+        };
+
+        if stop == JobStop::Paused {
+            jobs.insert(game_id.to_string(), Job {
+                state: JobState::Paused,
+                cancel: CancelHandle::default(),
+                stop,
+            });
+        }
+        stop
+    }
+
+    // Возвращает прежнее состояние: приостановленное обновление снимается сразу
+    pub fn cancel(&self, game_id: &str) -> Result<JobState> {
+        let mut jobs = self.jobs.lock();
+        let job = jobs.get_mut(game_id).ok_or_else(|| not_running(game_id))?;
+        let state = job.state;
+
+        match state {
+            JobState::Running => {
+                job.stop = JobStop::Cancelled;
+                job.cancel.cancel();
+            }
+            JobState::Paused => {
+                jobs.remove(game_id);
+            }
+        }
+        Ok(state)
+    }
+
+    pub fn pause(&self, game_id: &str) -> Result<()> {
+        let mut jobs = self.jobs.lock();
+        let job = jobs
+            .get_mut(game_id)
+            .filter(|job| job.state == JobState::Running)
+            .ok_or_else(|| not_running(game_id))?;
+
+        job.stop = JobStop::Paused;
+        job.cancel.cancel();
+        Ok(())
+    }
+
+    pub fn state(&self, game_id: &str) -> Option<JobState> {
+        self.jobs.lock().get(game_id).map(|job| job.state)
+    }
+}
+
+fn not_running(game_id: &str) -> Error {
+    Error::UpdateFailed(format!("No update in progress for {}", game_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_start_of_running_update_fails() {
+        let jobs = UpdateJobs::default();
+        jobs.start("570").unwrap();
+
+        assert!(jobs.start("570").is_err());
+        assert_eq!(jobs.state("570"), Some(JobState::Running));
+
+        assert_eq!(jobs.finish("570"), JobStop::Finished);
+        assert_eq!(jobs.state("570"), None);
+    }
+
+    #[test]
+    fn paused_update_is_kept_until_resumed() {
+        let jobs = UpdateJobs::default();
+        let cancel = jobs.start("570").unwrap();
+
+        jobs.pause("570").unwrap();
+        assert!(cancel.is_cancelled());
+        // Процесс ещё завершается: до finish обновление считается запущенным
+        assert_eq!(jobs.state("570"), Some(JobState::Running));
+
+        assert_eq!(jobs.finish("570"), JobStop::Paused);
+        assert_eq!(jobs.state("570"), Some(JobState::Paused));
+        assert!(jobs.pause("570").is_err());
+
+        // Продолжение - новый запуск со своим handle
+        let resumed = jobs.start("570").unwrap();
+        assert!(!resumed.is_cancelled());
+        assert_eq!(jobs.state("570"), Some(JobState::Running));
+        assert_eq!(jobs.finish("570"), JobStop::Finished);
+        assert_eq!(jobs.state("570"), None);
+    }
+
+    #[test]
+    fn cancel_stops_running_update() {
+        let jobs = UpdateJobs::default();
+        let cancel = jobs.start("570").unwrap();
+
+        assert_eq!(jobs.cancel("570").unwrap(), JobState::Running);
+        assert!(cancel.is_cancelled());
+        assert_eq!(jobs.finish("570"), JobStop::Cancelled);
+        assert_eq!(jobs.state("570"), None);
+    }
+
+    #[test]
+    fn cancel_removes_paused_update() {
+        let jobs = UpdateJobs::default();
+        jobs.start("570").unwrap();
+        jobs.pause("570").unwrap();
+        jobs.finish("570");
+
+        assert_eq!(jobs.cancel("570").unwrap(), JobState::Paused);
+        assert_eq!(jobs.state("570"), None);
+        assert!(jobs.cancel("570").is_err());
+    }
+
+    #[test]
+    fn unknown_update_cannot_be_paused_or_cancelled() {
+        let jobs = UpdateJobs::default();
+        assert!(jobs.pause("570").is_err());
+        assert!(jobs.cancel("570").is_err());
+        assert_eq!(jobs.finish("570"), JobStop::Finished);
+    }
+}
@@ -1,30 +1,105 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use chrono::Utc;
+use parking_lot::Mutex;
 use tauri::{AppHandle, Emitter};
 use crate::error::{Result, Error};
+use crate::history::{HistoryOutcome, HistoryService, HistoryStatus};
+use crate::logging::redact;
+use crate::process;
 use crate::settings::Settings;
+use crate::{log_error, log_info, log_warn};
+use crate::steam::{BuildComparison, CancelHandle, SteamManager, UpdateState};
 use std::path::PathBuf;
-use super::{Game, UpdateProgress, steam, epic};
+use super::jobs::{JobState, JobStop, UpdateJobs};
+use super::retry::RetryPolicy;
+use super::{Attempt, Game, Platform, UpdateAttempt, UpdateCheck, UpdateProgress, UpdateStatus, steam, epic};
+
+// Как часто проверяем, закрыта ли игра, обновление которой отложено
+const GAME_EXIT_POLL: Duration = Duration::from_secs(5);
+
+// Данные одного запуска обновления для итогового события и истории
+#[derive(Default)]
+struct UpdateRun {
+    attempts: Vec<UpdateAttempt>,
+    downloaded: Arc<AtomicU64>,
+    platform: Option<Platform>,
+    history_id: Option<i64>,
+}
 
 #[derive(Clone)]
 pub struct GameManager {
     settings: Arc<Settings>,
     app: Arc<AppHandle>,
+    steam: SteamManager,
+    jobs: Arc<UpdateJobs>,
+    retry: Arc<Mutex<RetryPolicy>>,
+    history: HistoryService,
 }
 
 impl GameManager {
-    pub fn new(settings: Settings, app: AppHandle) -> Self {
+    pub fn new(settings: Settings, history: HistoryService, app: AppHandle) -> Self {
+        let steam = SteamManager::new(settings.clone(), steam::bundled_steamcmd_path(&app));
+
+        let emitter = app.clone();
+        steam.on_steam_guard_required(move |request| {
+            if let Err(e) = emitter.emit("steam-guard-required", request) {
+                log_error!("Failed to emit steam-guard-required: {}", e);
+            }
+        });
+
         Self {
+            retry: Arc::new(Mutex::new(settings.retry.clone())),
             settings: Arc::new(settings),
             app: Arc::new(app),
+            steam,
+            jobs: Arc::new(UpdateJobs::default()),
+            history,
         }
     }
 
+    // Новая политика повторов и данные входа Steam действуют со следующего обновления.
+    // В settings должен быть пароль из хранилища секретов, см. SettingsManager::with_password
+    pub fn apply_settings(&self, settings: &Settings) {
+        *self.retry.lock() = settings.retry.clone();
+        self.steam.apply_settings(settings);
+    }
+
+    // Список для интерфейса: с отметкой о запущенных играх и временем из истории обновлений
     pub async fn get_installed_games(&self) -> Result<Vec<Game>> {
+        let mut games = self.installed_games().await?;
+
+        let processes = process::list_processes();
+        for game in &mut games {
+            game.running = process::running_under(&processes, &game.install_path)
+                .next()
+                .is_some();
+        }
+
+        // Клиент Steam мог обновить игру сам, поэтому берём более позднее время
+        match self.history.last_updates().await {
+            Ok(last_updates) => {
+                for game in &mut games {
+                    if let Some(time) = last_updates.get(&game.id) {
+                        game.last_update = game.last_update.max(Some(*time));
+                    }
+                }
+            }
+            Err(e) => log_warn!("Failed to read update history: {}", e),
+        }
+
+        Ok(games)
+    }
+
+    // Только сведения из манифестов: без обхода процессов и запроса к истории
+    pub async fn installed_games(&self) -> Result<Vec<Game>> {
         let mut games = Vec::new();
 
         // Получаем игры Steam
         if let Some(_path) = &self.settings.paths.steam {
-            let steam_games = steam::get_installed_games(&self.app).await?;
+            let steam_games = steam::get_installed_games(&self.steam, &self.app).await?;
             games.extend(steam_games);
         }
 
@@ -41,42 +116,311 @@ impl GameManager {
         self.get_installed_games().await
     }
 
-    pub async fn update_game(&self, game_id: &str) -> Result<()> {
-        let games = self.get_installed_games().await?;
-        let game = games
-            .iter()
+    // Запущена ли игра, вызывающий проверяет сам через process::processes_under
+    pub async fn find_game(&self, game_id: &str) -> Result<Game> {
+        self.installed_games()
+            .await?
+            .into_iter()
             .find(|g| g.id == game_id)
-            .ok_or_else(|| Error::ProcessError(format!("Game not found: {}", game_id)))?;
+            .ok_or_else(|| Error::ProcessError(format!("Game not found: {}", game_id)))
+    }
+
+    pub async fn update_game(&self, game_id: &str) -> Result<()> {
+        let cancel = self.jobs.start(game_id)?;
+        self.run_update(game_id, cancel).await
+    }
+
+    async fn run_update(&self, game_id: &str, cancel: CancelHandle) -> Result<()> {
+        let mut run = UpdateRun::default();
+        let result = self.update_with(game_id, cancel, &mut run).await;
+        let stop = self.jobs.finish(game_id);
+        self.record_history(game_id, &run, stop, &result).await;
+
+        let attempts = run.attempts;
+        match (stop, result) {
+            // steamcmd продолжит загрузку с сохранённого места при следующем app_update
+            (JobStop::Paused, Err(Error::Cancelled)) => {
+                log_info!("Update of {} paused", game_id);
+                self.emit_update_progress(UpdateProgress::stopped(
+                    game_id,
+                    UpdateState::Paused,
+                    "Обновление приостановлено",
+                ))
+            }
+            (_, Err(Error::Cancelled)) => {
+                log_info!("Update of {} cancelled", game_id);
+                self.emit_update_progress(UpdateProgress::stopped(
+                    game_id,
+                    UpdateState::Cancelled,
+                    "Обновление отменено",
+                ))?;
+                Err(Error::Cancelled)
+            }
+            (_, Ok(())) => {
+                log_info!("Update of {} completed", game_id);
+                self.emit_update_progress(UpdateProgress {
+                    progress: 100.0,
+                    status: UpdateStatus {
+                        is_updating: false,
+                        progress: Some(100.0),
+                        error: None,
+                    },
+                    attempts,
+                    ..UpdateProgress::stopped(game_id, UpdateState::Complete, "Обновление завершено")
+                })
+            }
+            (_, Err(e)) => {
+                self.emit_update_progress(UpdateProgress {
+                    attempts,
+                    ..UpdateProgress::failed(
+                        game_id,
+                        UpdateState::Error,
+                        e.hint().unwrap_or("Ошибка обновления"),
+                        &e,
+                    )
+                })?;
+                Err(e)
+            }
+        }
+    }
+
+    // Повторяет обновление после временных ошибок по политике из настроек.
+    // Каждая попытка записывается в attempts
+    async fn update_with(
+        &self,
+        game_id: &str,
+        cancel: CancelHandle,
+        run: &mut UpdateRun,
+    ) -> Result<()> {
+        let game = &self.find_game(game_id).await?;
+        run.platform = Some(game.platform);
+        run.history_id = self
+            .history
+            .start(game)
+            .await
+            .inspect_err(|e| log_warn!("Failed to record update of {} in history: {}", game_id, e))
+            .ok();
+
+        let policy = self.retry.lock().clone();
+        let mut attempt = Attempt { number: 1, max: policy.max_attempts() };
+
+        loop {
+            self.wait_for_exit(game, &cancel).await?;
+
+            let started_at = Utc::now();
+            let result = self
+                .update_once(game, cancel.clone(), attempt, run.downloaded.clone())
+                .await;
+            let retryable = result.as_ref().is_err_and(|e| e.is_retryable());
+            run.attempts.push(UpdateAttempt {
+                attempt: attempt.number,
+                started_at,
+                finished_at: Utc::now(),
+                error: result.as_ref().err().map(|e| redact(&e.to_string()).into_owned()),
+                retryable,
+            });
+
+            let error = match result {
+                Err(e) if retryable && attempt.number < attempt.max => e,
+                result => return result,
+            };
+
+            let delay = policy.delay(attempt.number);
+            log_warn!(
+                "Update of {} failed (attempt {}/{}), retrying in {:?}: {}",
+                game_id, attempt.number, attempt.max, delay, error
+            );
+            self.emit_update_progress(UpdateProgress {
+                attempt: Some(attempt),
+                ..UpdateProgress::failed(
+                    game_id,
+                    UpdateState::Retrying,
+                    &format!(
+                        "Попытка {}/{} не удалась, повтор через {} с",
+                        attempt.number, attempt.max, delay.as_secs()
+                    ),
+                    &error,
+                )
+            })?;
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancel.cancelled() => return Err(Error::Cancelled),
+            }
+            attempt.number += 1;
+        }
+    }
 
+    async fn update_once(
+        &self,
+        game: &Game,
+        cancel: CancelHandle,
+        attempt: Attempt,
+        downloaded: Arc<AtomicU64>,
+    ) -> Result<()> {
         match game.platform {
-            super::Platform::Steam => {
-                steam::update_game(game_id, &self.app.as_ref()).await?;
+            Platform::Steam => {
+                steam::update_game(&self.steam, game, cancel, attempt, downloaded, self.app.as_ref()).await
+            }
+            Platform::Epic => {
+                epic::update_game(&game.id, cancel, attempt, self.app.as_ref()).await
             }
-            super::Platform::Epic => {
-                epic::update_game(game_id, &self.app.as_ref()).await?;
+        }
+    }
+
+    // Ошибка записи в историю не влияет на результат обновления
+    async fn record_history(&self, game_id: &str, run: &UpdateRun, stop: JobStop, result: &Result<()>) {
+        let Some(id) = run.history_id else {
+            return;
+        };
+
+        let status = match (stop, result) {
+            (_, Ok(())) => HistoryStatus::Success,
+            (JobStop::Paused, Err(Error::Cancelled)) => HistoryStatus::Paused,
+            (_, Err(Error::Cancelled)) => HistoryStatus::Cancelled,
+            (_, Err(_)) => HistoryStatus::Failed,
+        };
+        let new_build_id = match (result, run.platform) {
+            (Ok(()), Some(Platform::Steam)) => steam::installed_build(&self.steam, game_id),
+            _ => None,
+        };
+
+        let outcome = HistoryOutcome {
+            status,
+            error: result
+                .as_ref()
+                .err()
+                .filter(|e| !matches!(e, Error::Cancelled))
+                .map(|e| redact(&e.to_string()).into_owned()),
+            attempts: run.attempts.len() as u32,
+            bytes_downloaded: Some(run.downloaded.load(Ordering::Relaxed)).filter(|&bytes| bytes > 0),
+            new_build_id,
+        };
+        if let Err(e) = self.history.finish(id, &outcome).await {
+            log_warn!("Failed to record result of {} update in history: {}", game_id, e);
+        }
+    }
+
+    // Обновление запущенной игры портит файлы, поэтому ждём её закрытия.
+    // Ожидание можно прервать через cancel_update или закрыть игру через close_game
+    async fn wait_for_exit(&self, game: &Game, cancel: &CancelHandle) -> Result<()> {
+        let mut notified = false;
+        loop {
+            let running = process::processes_under(&game.install_path);
+            if running.is_empty() {
+                if notified {
+                    log_info!("{} was closed, starting update", game.name);
+                }
+                return Ok(());
+            }
+
+            if !notified {
+                let names: Vec<_> = running.iter().map(|p| p.name.as_str()).collect();
+                log_info!("{} is running ({}), update deferred", game.name, names.join(", "));
+                self.emit_update_progress(UpdateProgress::stopped(
+                    &game.id,
+                    UpdateState::GameRunning,
+                    "Игра запущена, обновление начнётся после её закрытия",
+                ))?;
+                notified = true;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(GAME_EXIT_POLL) => {}
+                _ = cancel.cancelled() => return Err(Error::Cancelled),
+            }
+        }
+    }
+
+    // Просит процессы игры завершиться; отложенное обновление начнётся само
+    pub async fn close_game(&self, game_id: &str) -> Result<()> {
+        let game = self.find_game(game_id).await?;
+        let running = process::processes_under(&game.install_path);
+        if running.is_empty() {
+            return Err(Error::ProcessError(format!("{} is not running", game.name)));
+        }
+
+        for process in running {
+            log_info!("Closing {} (pid {})", process.name, process.pid);
+            if let Err(e) = process::terminate(process.pid).await {
+                log_warn!("Failed to close {} (pid {}): {}", process.name, process.pid, e);
             }
         }
+        Ok(())
+    }
 
+    pub fn cancel_update(&self, game_id: &str) -> Result<()> {
+        // Запущенное обновление сообщит об отмене само, когда процесс завершится
+        if self.jobs.cancel(game_id)? == JobState::Paused {
+            self.emit_update_progress(UpdateProgress::stopped(
+                game_id,
+                UpdateState::Cancelled,
+                "Обновление отменено",
+            ))?;
+        }
         Ok(())
     }
 
-    pub async fn check_game_updates(&self, game_id: &str) -> Result<bool> {
-        let games = self.get_installed_games().await?;
-        let game = games
-            .iter()
-            .find(|g| g.id == game_id)
-            .ok_or_else(|| Error::ProcessError(format!("Game not found: {}", game_id)))?;
+    pub fn pause_update(&self, game_id: &str) -> Result<()> {
+        self.jobs.pause(game_id)
+    }
+
+    // Приостановленное обновление возобновляет очередь, см. QueueManager::resume
+    pub fn is_paused(&self, game_id: &str) -> bool {
+        self.jobs.state(game_id) == Some(JobState::Paused)
+    }
+
+    pub async fn check_game_updates(&self, game_id: &str) -> Result<UpdateCheck> {
+        let game = &self.find_game(game_id).await?;
 
         match game.platform {
-            super::Platform::Steam => {
-                steam::check_updates(game_id, &self.app).await
+            Platform::Steam => {
+                steam::check_updates(&self.steam, game_id).await
             }
-            super::Platform::Epic => {
-                epic::check_updates(game_id, &self.app.as_ref()).await
+            Platform::Epic => {
+                let needs_update = epic::check_updates(game_id, self.app.as_ref()).await?;
+                Ok(UpdateCheck {
+                    game_id: game_id.to_string(),
+                    needs_update,
+                    steam: None,
+                })
             }
         }
     }
 
+    // Сверка с актуальной сборкой через steamcmd (app_info_print)
+    pub async fn compare_game_builds(&self, game_id: &str) -> Result<BuildComparison> {
+        let app_id = game_id
+            .parse::<u32>()
+            .map_err(|_| Error::SteamError(format!("Invalid Steam app id: {}", game_id)))?;
+        self.steam.compare_builds(app_id).await
+    }
+
+    // Сверка нескольких игр Steam одним запросом к steamcmd
+    pub async fn compare_game_builds_batch(&self, game_ids: &[String]) -> Result<HashMap<String, Result<BuildComparison>>> {
+        let mut results = HashMap::new();
+        let mut app_ids = Vec::new();
+        for game_id in game_ids {
+            match game_id.parse::<u32>() {
+                Ok(app_id) => app_ids.push(app_id),
+                Err(_) => {
+                    results.insert(
+                        game_id.clone(),
+                        Err(Error::SteamError(format!("Invalid Steam app id: {}", game_id))),
+                    );
+                }
+            }
+        }
+
+        let comparisons = self.steam.compare_builds_batch(&app_ids).await?;
+        results.extend(comparisons.into_iter().map(|(app_id, result)| (app_id.to_string(), result)));
+        Ok(results)
+    }
+
+    pub fn submit_steam_guard_code(&self, code: &str) -> Result<()> {
+        self.steam.submit_steam_guard_code(code)
+    }
+
     pub fn emit_update_progress(&self, progress: UpdateProgress) -> Result<()> {
         self.app.as_ref()
             .emit("update-progress", &progress)
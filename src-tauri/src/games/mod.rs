@@ -1,19 +1,43 @@
 use serde::{Serialize, Serializer};
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
+use crate::error::{Error, SteamCmdFailure};
+use crate::registry::steam::SteamInstallState;
+use crate::logging::redact;
+use crate::steam::UpdateState;
 
 pub mod manager;
+pub mod jobs;
+pub mod retry;
 pub mod steam;
 pub mod epic;
 
 pub use manager::GameManager;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum Platform {
     Steam,
     Epic,
 }
 
+impl Platform {
+    // Имя платформы в таблицах settings.db
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Steam => "steam",
+            Platform::Epic => "epic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "steam" => Some(Platform::Steam),
+            "epic" => Some(Platform::Epic),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct UpdateStatus {
     pub is_updating: bool,
@@ -21,12 +45,95 @@ pub struct UpdateStatus {
     pub error: Option<String>,
 }
 
+// Номер текущей попытки обновления из max
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Attempt {
+    pub number: u32,
+    pub max: u32,
+}
+
+// Итог одной попытки, передаётся в завершающем событии
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateAttempt {
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub retryable: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct UpdateProgress {
     pub game_id: String,
     pub progress: f32,
+    pub state: UpdateState,
     pub status: UpdateStatus,
     pub message: Option<String>,
+    pub bytes_downloaded: Option<u64>,
+    pub bytes_total: Option<u64>,
+    pub attempt: Option<Attempt>,
+    // Заполняется только в событиях Complete и Error
+    pub attempts: Vec<UpdateAttempt>,
+    // Причина ошибки в событиях Error и Retrying, как в CommandError
+    pub kind: Option<SteamCmdFailure>,
+    pub retryable: bool,
+    pub hint: Option<&'static str>,
+}
+
+impl UpdateProgress {
+    // Итоговое событие без данных о загрузке: пауза или отмена
+    pub fn stopped(game_id: &str, state: UpdateState, message: &str) -> Self {
+        Self {
+            game_id: game_id.to_string(),
+            progress: 0.0,
+            state,
+            status: UpdateStatus {
+                is_updating: false,
+                progress: None,
+                error: None,
+            },
+            message: Some(message.to_string()),
+            bytes_downloaded: None,
+            bytes_total: None,
+            attempt: None,
+            attempts: Vec::new(),
+            kind: None,
+            retryable: false,
+            hint: None,
+        }
+    }
+
+    // Событие Error или Retrying с причиной ошибки
+    pub fn failed(game_id: &str, state: UpdateState, message: &str, error: &Error) -> Self {
+        Self {
+            status: UpdateStatus {
+                is_updating: state == UpdateState::Retrying,
+                progress: None,
+                error: Some(redact(&error.to_string()).into_owned()),
+            },
+            kind: error.kind(),
+            retryable: error.is_retryable(),
+            hint: error.hint(),
+            ..Self::stopped(game_id, state, message)
+        }
+    }
+}
+
+// Данные из appmanifest_<appid>.acf
+#[derive(Debug, Clone, Serialize)]
+pub struct SteamDetails {
+    pub build_id: u32,
+    pub size_on_disk: u64,
+    pub last_updated: Option<DateTime<Utc>>,
+    pub installed_depots: Vec<u32>,
+    pub state: SteamInstallState,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateCheck {
+    pub game_id: String,
+    pub needs_update: bool,
+    pub steam: Option<SteamInstallState>,
 }
 
 #[derive(Debug, Clone)]
@@ -37,6 +144,9 @@ pub struct Game {
     pub install_path: PathBuf,
     pub last_update: Option<DateTime<Utc>>,
     pub update_status: Option<UpdateStatus>,
+    pub steam: Option<SteamDetails>,
+    // Запущен процесс из каталога игры; обновление будет отложено
+    pub running: bool,
 }
 
 impl Serialize for Game {
@@ -45,13 +155,15 @@ impl Serialize for Game {
         S: Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("Game", 6)?;
+        let mut state = serializer.serialize_struct("Game", 8)?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("name", &self.name)?;
         state.serialize_field("platform", &self.platform)?;
         state.serialize_field("install_path", &self.install_path.to_string_lossy())?;
         state.serialize_field("last_update", &self.last_update)?;
         state.serialize_field("update_status", &self.update_status)?;
+        state.serialize_field("steam", &self.steam)?;
+        state.serialize_field("running", &self.running)?;
         state.end()
     }
 } 
\ No newline at end of file
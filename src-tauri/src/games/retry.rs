@@ -0,0 +1,103 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+// Повтор обновления после временных ошибок (Error::is_retryable):
+// задержка растёт в multiplier раз, но не выше max_delay_secs, и
+// случайно отклоняется на ±jitter, чтобы повторы не шли синхронно
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Всего попыток, включая первую
+    pub max_attempts: u32,
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+    pub multiplier: f64,
+    // Доля от задержки, 0.0..=1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay_secs: 15,
+            max_delay_secs: 300,
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts.max(1)
+    }
+
+    // Задержка перед попыткой, следующей за attempt (нумерация с 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = (self.initial_delay_secs as f64 * self.multiplier.max(1.0).powi(exponent))
+            .min(self.max_delay_secs as f64);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * random_unit() - 1.0);
+        // Переполнение или NaN из неверных настроек дают максимальную задержку
+        Duration::try_from_secs_f64(base * factor).unwrap_or(Duration::from_secs(self.max_delay_secs))
+    }
+}
+
+// Для разброса задержки криптостойкость не нужна
+fn random_unit() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    nanos as f64 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            jitter,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn delay_grows_and_is_capped() {
+        let policy = policy(0.0);
+        let delays: Vec<_> = (1..=7).map(|attempt| policy.delay(attempt).as_secs()).collect();
+        assert_eq!(delays, [15, 30, 60, 120, 240, 300, 300]);
+        assert_eq!(policy.delay(0), Duration::from_secs(15));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = policy(0.5);
+        for _ in 0..100 {
+            let delay = policy.delay(2).as_secs_f64();
+            assert!((15.0..=45.0).contains(&delay), "{}", delay);
+        }
+    }
+
+    #[test]
+    fn invalid_values_fall_back_to_max_delay() {
+        let policy = RetryPolicy {
+            jitter: f64::NAN,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(300));
+
+        let policy = RetryPolicy {
+            max_delay_secs: u64::MAX,
+            multiplier: f64::INFINITY,
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(3), Duration::from_secs(u64::MAX));
+    }
+}
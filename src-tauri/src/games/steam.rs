@@ -1,14 +1,17 @@
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tauri::path::BaseDirectory;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::process::Command;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use chrono::DateTime;
 use crate::cache::{Cache, TimedCacheEntry};
 use crate::error::{Result, Error};
-use crate::registry::steam::SteamRegistry;
-use crate::registry::RegistryReader;
-use super::{Game, Platform, UpdateProgress, UpdateStatus};
+use crate::steam::{CancelHandle, SteamManager, UpdateState, UpdateStatus as SteamUpdateStatus, STEAMCMD_BINARY};
+use crate::log_warn;
+use crate::logging::redact;
+use super::{Attempt, Game, Platform, SteamDetails, UpdateCheck, UpdateProgress, UpdateStatus};
 
 // Структуры для десериализации JSON
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -32,131 +35,154 @@ lazy_static::lazy_static! {
     static ref STEAM_APPS_CACHE: Cache<String, TimedCacheEntry<SteamAppListRoot>> = Cache::new(1);
 }
 
-fn get_steamcmd_path(app: &AppHandle) -> std::result::Result<PathBuf, tauri::Error> {
-    app.path().resolve("resources/bin/steamcmd/steamcmd.exe", BaseDirectory::Resource)
-}
-
 fn get_steam_games_list_path(app: &AppHandle) -> std::result::Result<PathBuf, tauri::Error> {
     app.path().resolve("resources/bin/steamcmd/games_list.json", BaseDirectory::Resource)
 }
 
-pub(crate) async fn get_installed_games(app: &AppHandle) -> Result<Vec<Game>> {
-    let registry = SteamRegistry::new()?;
-    let games = registry.get_installed_games()?;
-    
+// steamcmd из ресурсов приложения
+pub(crate) fn bundled_steamcmd_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .resolve(format!("resources/bin/steamcmd/{}", STEAMCMD_BINARY), BaseDirectory::Resource)
+        .ok()
+}
+
+pub(crate) async fn get_installed_games(steam: &SteamManager, app: &AppHandle) -> Result<Vec<Game>> {
     let mut result = Vec::new();
-    for (name, path) in games {
-        // Получаем Steam ID игры
-        if let Some(app_id) = get_steam_app_id(&name, app).await? {
-            result.push(Game {
-                id: app_id.to_string(),
-                name,
-                platform: Platform::Steam,
-                install_path: path,
-                last_update: None,
-                update_status: None,
-            });
-        }
+    for installed in steam.get_installed_games()? {
+        let manifest = installed.manifest;
+
+        // Список игр нужен только если в манифесте нет названия
+        let name = if installed.name.is_empty() {
+            get_steam_app_name(installed.app_id, app)
+                .await
+                .unwrap_or_else(|e| {
+                    log_warn!("Failed to look up name for app {}: {}", installed.app_id, e);
+                    None
+                })
+                .unwrap_or_else(|| manifest.install_dir.clone())
+        } else {
+            installed.name
+        };
+
+        let last_updated = DateTime::from_timestamp(manifest.last_updated, 0);
+
+        result.push(Game {
+            id: installed.app_id.to_string(),
+            name,
+            platform: Platform::Steam,
+            install_path: installed.install_dir,
+            last_update: last_updated,
+            update_status: None,
+            steam: Some(SteamDetails {
+                build_id: manifest.build_id,
+                size_on_disk: manifest.size_on_disk,
+                last_updated,
+                installed_depots: manifest.installed_depots.keys().copied().collect(),
+                state: manifest.install_state(),
+            }),
+            running: false,
+        });
     }
-    
+
     Ok(result)
 }
 
-pub(crate) async fn update_game(game_id: &str, app: &AppHandle) -> Result<()> {
-    let steamcmd_path = get_steamcmd_path(app)?;
-
-    if !steamcmd_path.exists() {
-        return Err(Error::SteamCmdNotFound);
-    }
-    
-    // Эмитим начало обновления
-    app.emit("update-progress", UpdateProgress {
-        game_id: game_id.to_string(),
-        progress: 0.0,
-        status: UpdateStatus {
-            is_updating: true,
-            progress: Some(0.0),
-            error: None,
-        },
-        message: Some("Начало обновления...".to_string()),
-    })?;
-
-    // Запускаем обновление через SteamCMD
-    let output = Command::new(&steamcmd_path)
-        .args(&[
-            "+login", "anonymous",
-            "+app_update", game_id,
-            "validate",
-            "+quit"
-        ])
-        .output()
+pub(crate) async fn update_game(
+    steam: &SteamManager,
+    game: &Game,
+    cancel: CancelHandle,
+    attempt: Attempt,
+    downloaded: Arc<AtomicU64>,
+    app: &AppHandle,
+) -> Result<()> {
+    let app_id = parse_app_id(&game.id)?;
+
+    // steamcmd по умолчанию ставит в свой каталог, поэтому указываем путь игры.
+    // Свой манифест он пишет в <папка игры>/steamapps, его подхватывает
+    // SteamRegistry::find_app_manifest
+    let emitter = app.clone();
+    let game_id = game.id.clone();
+    steam
+        .update_game_with_progress(app_id, Some(&game.install_path), cancel, move |status| {
+            if let Some(bytes) = status.bytes_downloaded {
+                downloaded.fetch_max(bytes, Ordering::Relaxed);
+            }
+            // Итоговые Complete и Error вместе с попытками отправляет GameManager
+            if matches!(status.state, UpdateState::Complete | UpdateState::Error) {
+                return;
+            }
+            let progress = to_update_progress(&game_id, status, attempt);
+            if let Err(e) = emitter.emit("update-progress", progress) {
+                log_warn!("Failed to emit update progress: {}", e);
+            }
+        })
         .await
-        .map_err(|e| Error::ProcessError(e.to_string()))?;
-
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        app.emit("update-progress", UpdateProgress {
-            game_id: game_id.to_string(),
-            progress: 0.0,
-            status: UpdateStatus {
-                is_updating: false,
-                progress: None,
-                error: Some(error_msg.to_string()),
-            },
-            message: Some("Ошибка обновления".to_string()),
-        })?;
-        return Err(Error::UpdateFailed(error_msg.to_string()));
-    }
+}
 
-    // Эмитим завершение обновления
-    app.emit("update-progress", UpdateProgress {
-        game_id: game_id.to_string(),
-        progress: 100.0,
-        status: UpdateStatus {
-            is_updating: false,
-            progress: Some(100.0),
-            error: None,
-        },
-        message: Some("Обновление завершено".to_string()),
-    })?;
+pub(crate) async fn check_updates(steam: &SteamManager, game_id: &str) -> Result<UpdateCheck> {
+    let state = steam.get_install_state(parse_app_id(game_id)?)?;
 
-    Ok(())
+    Ok(UpdateCheck {
+        game_id: game_id.to_string(),
+        needs_update: state.needs_update,
+        steam: Some(state),
+    })
 }
 
-pub(crate) async fn check_updates(game_id: &str, app: &AppHandle) -> Result<bool> {
-    let steamcmd_path = get_steamcmd_path(app)?;
-
-    if !steamcmd_path.exists() {
-        return Err(Error::SteamCmdNotFound);
+// Сборка из манифеста, который steamcmd обновил
+pub(crate) fn installed_build(steam: &SteamManager, game_id: &str) -> Option<u32> {
+    let state = parse_app_id(game_id).and_then(|app_id| steam.get_install_state(app_id));
+    match state {
+        Ok(state) => Some(state.build_id),
+        Err(e) => {
+            log_warn!("Failed to read installed build of {}: {}", game_id, e);
+            None
+        }
     }
+}
 
-    let output = Command::new(&steamcmd_path)
-        .args(&[
-            "+login", "anonymous",
-            "+app_info_update", "1",
-            "+app_status", game_id,
-            "+quit"
-        ])
-        .output()
-        .await
-        .map_err(|e| Error::ProcessError(e.to_string()))?;
+fn parse_app_id(game_id: &str) -> Result<u32> {
+    game_id
+        .parse::<u32>()
+        .map_err(|_| Error::SteamError(format!("Invalid Steam app id: {}", game_id)))
+}
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    
-    // Проверяем наличие обновлений по выводу steamcmd
-    // Если в выводе есть "update available", значит есть обновление
-    Ok(output_str.contains("update available"))
+fn to_update_progress(game_id: &str, status: SteamUpdateStatus, attempt: Attempt) -> UpdateProgress {
+    let message = match status.state {
+        UpdateState::Starting if attempt.number > 1 => Some(format!(
+            "Начало обновления, попытка {}/{}...",
+            attempt.number, attempt.max
+        )),
+        UpdateState::Starting => Some("Начало обновления...".to_string()),
+        _ if status.status.is_empty() => None,
+        _ => Some(redact(&status.status).into_owned()),
+    };
+
+    UpdateProgress {
+        game_id: game_id.to_string(),
+        progress: status.progress,
+        bytes_downloaded: status.bytes_downloaded,
+        bytes_total: status.bytes_total,
+        state: status.state,
+        status: UpdateStatus {
+            is_updating: true,
+            progress: Some(status.progress),
+            error: status.error.map(|e| redact(&e).into_owned()),
+        },
+        message,
+        attempt: Some(attempt),
+        attempts: Vec::new(),
+        kind: None,
+        retryable: false,
+        hint: None,
+    }
 }
 
-async fn get_steam_app_id(name: &str, app: &AppHandle) -> Result<Option<u32>> {
+async fn get_steam_app_name(app_id: u32, app: &AppHandle) -> Result<Option<String>> {
     // Проверяем кэш
     if let Some(cached) = STEAM_APPS_CACHE.get(&"steam_apps".to_string()) {
         if cached.timestamp > chrono::Utc::now() {
-            // Ищем игру в кэшированном списке
-            if let Some(app) = cached.value.applist.apps.iter().find(|app| app.name.to_lowercase() == name.to_lowercase()) {
-                return Ok(Some(app.appid));
-            }
-            return Ok(None);
+            return Ok(find_app_name(&cached.value, app_id));
         }
     }
 
@@ -168,16 +194,23 @@ async fn get_steam_app_id(name: &str, app: &AppHandle) -> Result<Option<u32>> {
     let app_list: SteamAppListRoot = serde_json::from_str(&content)
         .map_err(|e| Error::ParseError(format!("Failed to parse games list: {}", e)))?;
 
+    let name = find_app_name(&app_list, app_id);
+
     // Кэшируем результат на 24 часа
     STEAM_APPS_CACHE.set(
         "steam_apps".to_string(),
-        TimedCacheEntry::new(app_list.clone(), 24 * 60)
+        TimedCacheEntry::new(app_list, 24 * 60)
     );
 
-    // Ищем игру в списке
-    if let Some(app) = app_list.applist.apps.iter().find(|app| app.name.to_lowercase() == name.to_lowercase()) {
-        Ok(Some(app.appid))
-    } else {
-        Ok(None)
-    }
-} 
\ No newline at end of file
+    Ok(name)
+}
+
+fn find_app_name(app_list: &SteamAppListRoot, app_id: u32) -> Option<String> {
+    app_list
+        .applist
+        .apps
+        .iter()
+        .find(|app| app.appid == app_id)
+        .map(|app| app.name.clone())
+}
+
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use sqlx::{Pool, Sqlite, Row};
+use sqlx::sqlite::SqliteRow;
+use chrono::{DateTime, Utc};
+use crate::error::{Result, Error};
+use crate::games::{Game, Platform};
+use super::{HistoryEntry, HistoryOutcome, HistoryPage, HistoryQuery, HistoryStatus};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+// Фильтры с NULL не ограничивают выборку
+const FILTER: &str = "(?1 IS NULL OR game_id = ?1)
+    AND (?2 IS NULL OR started_at >= ?2)
+    AND (?3 IS NULL OR started_at <= ?3)
+    AND (?4 IS NULL OR status = ?4)";
+
+// Таблица создаётся миграцией, см. migrations
+pub async fn init_history(pool: &Pool<Sqlite>) -> Result<()> {
+    // Запуски, не завершившиеся до закрытия приложения
+    sqlx::query("UPDATE update_history SET status = 'interrupted' WHERE status = 'running'")
+        .execute(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+pub async fn insert_started(pool: &Pool<Sqlite>, game: &Game) -> Result<i64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO update_history (game_id, game_name, platform, started_at, status, old_build_id)
+        VALUES (?, ?, ?, ?, 'running', ?)
+        "#,
+    )
+    .bind(&game.id)
+    .bind(&game.name)
+    .bind(game.platform.as_str())
+    .bind(Utc::now().timestamp())
+    .bind(game.steam.as_ref().map(|steam| steam.build_id as i64))
+    .execute(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn finish(pool: &Pool<Sqlite>, id: i64, outcome: &HistoryOutcome) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE update_history
+        SET finished_at = ?, status = ?, error = ?, attempts = ?, bytes_downloaded = ?, new_build_id = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(Utc::now().timestamp())
+    .bind(outcome.status.as_str())
+    .bind(&outcome.error)
+    .bind(outcome.attempts as i64)
+    .bind(outcome.bytes_downloaded.map(|bytes| bytes as i64))
+    .bind(outcome.new_build_id.map(|build| build as i64))
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+// Новые запуски первыми
+pub async fn query(pool: &Pool<Sqlite>, query: &HistoryQuery) -> Result<HistoryPage> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0);
+
+    let total: i64 = sqlx::query(&format!("SELECT COUNT(*) FROM update_history WHERE {}", FILTER))
+        .bind(&query.game_id)
+        .bind(query.from.map(|from| from.timestamp()))
+        .bind(query.to.map(|to| to.timestamp()))
+        .bind(query.status.map(|status| status.as_str()))
+        .fetch_one(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?
+        .get(0);
+
+    let rows = sqlx::query(&format!(
+        "SELECT * FROM update_history WHERE {} ORDER BY started_at DESC, id DESC LIMIT ?5 OFFSET ?6",
+        FILTER
+    ))
+    .bind(&query.game_id)
+    .bind(query.from.map(|from| from.timestamp()))
+    .bind(query.to.map(|to| to.timestamp()))
+    .bind(query.status.map(|status| status.as_str()))
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(HistoryPage {
+        entries: rows.iter().map(entry_from_row).collect::<Result<_>>()?,
+        total: total as u64,
+    })
+}
+
+// Время последнего успешного обновления каждой игры
+pub async fn last_updates(pool: &Pool<Sqlite>) -> Result<HashMap<String, DateTime<Utc>>> {
+    let rows = sqlx::query(
+        "SELECT game_id, MAX(finished_at) AS finished_at FROM update_history
+         WHERE status = 'success' GROUP BY game_id",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let finished_at = DateTime::from_timestamp(row.get("finished_at"), 0)?;
+            Some((row.get("game_id"), finished_at))
+        })
+        .collect())
+}
+
+fn entry_from_row(row: &SqliteRow) -> Result<HistoryEntry> {
+    let platform: String = row.get("platform");
+    let status: String = row.get("status");
+    let finished_at: Option<i64> = row.get("finished_at");
+    let attempts: i64 = row.get("attempts");
+    let bytes_downloaded: Option<i64> = row.get("bytes_downloaded");
+    let old_build_id: Option<i64> = row.get("old_build_id");
+    let new_build_id: Option<i64> = row.get("new_build_id");
+
+    Ok(HistoryEntry {
+        id: row.get("id"),
+        game_id: row.get("game_id"),
+        game_name: row.get("game_name"),
+        platform: Platform::from_name(&platform)
+            .ok_or_else(|| Error::Database(format!("Unknown platform in history: {}", platform)))?,
+        started_at: DateTime::from_timestamp(row.get("started_at"), 0),
+        finished_at: finished_at.and_then(|time| DateTime::from_timestamp(time, 0)),
+        status: HistoryStatus::from_name(&status)
+            .ok_or_else(|| Error::Database(format!("Unknown status in history: {}", status)))?,
+        error: row.get("error"),
+        attempts: attempts as u32,
+        bytes_downloaded: bytes_downloaded.map(|bytes| bytes as u64),
+        old_build_id: old_build_id.map(|build| build as u32),
+        new_build_id: new_build_id.map(|build| build as u32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use super::*;
+
+    async fn pool() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::migrations::run(&pool, Path::new("memory.db")).await.unwrap();
+        pool
+    }
+
+    fn game(id: &str) -> Game {
+        Game {
+            id: id.to_string(),
+            name: format!("Game {}", id),
+            platform: Platform::Steam,
+            install_path: PathBuf::from("/games").join(id),
+            last_update: None,
+            update_status: None,
+            steam: None,
+            running: false,
+        }
+    }
+
+    fn outcome(status: HistoryStatus) -> HistoryOutcome {
+        HistoryOutcome {
+            status,
+            error: (status == HistoryStatus::Failed).then(|| "Disk write failure".to_string()),
+            attempts: 1,
+            bytes_downloaded: Some(1024),
+            new_build_id: (status == HistoryStatus::Success).then_some(200),
+        }
+    }
+
+    // Запуск с заданным временем начала и окончания (секунды от начала эпохи)
+    async fn record(pool: &Pool<Sqlite>, game_id: &str, started_at: i64, status: HistoryStatus) -> i64 {
+        let id = insert_started(pool, &game(game_id)).await.unwrap();
+        if status != HistoryStatus::Running {
+            finish(pool, id, &outcome(status)).await.unwrap();
+        }
+        sqlx::query("UPDATE update_history SET started_at = ?1, finished_at = ?1 + 60 WHERE id = ?2")
+            .bind(started_at)
+            .bind(id)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    fn time(timestamp: i64) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(timestamp, 0)
+    }
+
+    async fn ids(pool: &Pool<Sqlite>, query: HistoryQuery) -> (Vec<i64>, u64) {
+        let page = super::query(pool, &query).await.unwrap();
+        (page.entries.iter().map(|entry| entry.id).collect(), page.total)
+    }
+
+    #[tokio::test]
+    async fn run_is_recorded_with_outcome() {
+        let pool = pool().await;
+        let id = insert_started(&pool, &game("570")).await.unwrap();
+        finish(&pool, id, &outcome(HistoryStatus::Failed)).await.unwrap();
+
+        let page = super::query(&pool, &HistoryQuery::default()).await.unwrap();
+        assert_eq!(page.total, 1);
+        let entry = &page.entries[0];
+        assert_eq!(entry.game_id, "570");
+        assert_eq!(entry.game_name, "Game 570");
+        assert_eq!(entry.platform, Platform::Steam);
+        assert_eq!(entry.status, HistoryStatus::Failed);
+        assert_eq!(entry.error.as_deref(), Some("Disk write failure"));
+        assert_eq!(entry.bytes_downloaded, Some(1024));
+        assert!(entry.finished_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn filters_by_game_status_and_dates() {
+        let pool = pool().await;
+        let a1 = record(&pool, "a", 1_000, HistoryStatus::Success).await;
+        let b1 = record(&pool, "b", 2_000, HistoryStatus::Failed).await;
+        let a2 = record(&pool, "a", 3_000, HistoryStatus::Failed).await;
+        let b2 = record(&pool, "b", 4_000, HistoryStatus::Success).await;
+
+        // Новые первыми
+        assert_eq!(ids(&pool, HistoryQuery::default()).await, (vec![b2, a2, b1, a1], 4));
+
+        let by_game = HistoryQuery {
+            game_id: Some("a".to_string()),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(ids(&pool, by_game).await, (vec![a2, a1], 2));
+
+        let by_status = HistoryQuery {
+            status: Some(HistoryStatus::Failed),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(ids(&pool, by_status).await, (vec![a2, b1], 2));
+
+        // Границы включаются
+        let by_dates = HistoryQuery {
+            from: time(2_000),
+            to: time(3_000),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(ids(&pool, by_dates).await, (vec![a2, b1], 2));
+
+        let combined = HistoryQuery {
+            game_id: Some("b".to_string()),
+            from: time(2_001),
+            status: Some(HistoryStatus::Success),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(ids(&pool, combined).await, (vec![b2], 1));
+    }
+
+    #[tokio::test]
+    async fn pages_keep_total() {
+        let pool = pool().await;
+        let mut all = Vec::new();
+        for i in 0..5 {
+            all.push(record(&pool, "a", 1_000 + i, HistoryStatus::Success).await);
+        }
+        all.reverse();
+
+        let page = |limit, offset| HistoryQuery {
+            limit: Some(limit),
+            offset: Some(offset),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(ids(&pool, page(2, 0)).await, (all[0..2].to_vec(), 5));
+        assert_eq!(ids(&pool, page(2, 4)).await, (all[4..].to_vec(), 5));
+        assert_eq!(ids(&pool, page(2, 10)).await, (vec![], 5));
+        // Нулевой размер страницы поднимается до одной записи
+        assert_eq!(ids(&pool, page(0, 0)).await, (all[0..1].to_vec(), 5));
+    }
+
+    #[tokio::test]
+    async fn last_updates_count_only_successful_runs() {
+        let pool = pool().await;
+        record(&pool, "a", 1_000, HistoryStatus::Success).await;
+        record(&pool, "a", 3_000, HistoryStatus::Success).await;
+        record(&pool, "a", 5_000, HistoryStatus::Failed).await;
+        record(&pool, "b", 2_000, HistoryStatus::Cancelled).await;
+
+        let last = last_updates(&pool).await.unwrap();
+        assert_eq!(last.len(), 1);
+        assert_eq!(last.get("a").copied(), time(3_060));
+    }
+
+    #[tokio::test]
+    async fn unfinished_runs_are_marked_interrupted() {
+        let pool = pool().await;
+        let running = record(&pool, "a", 1_000, HistoryStatus::Running).await;
+        record(&pool, "b", 2_000, HistoryStatus::Success).await;
+
+        init_history(&pool).await.unwrap();
+
+        let interrupted = HistoryQuery {
+            status: Some(HistoryStatus::Interrupted),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(ids(&pool, interrupted).await, (vec![running], 1));
+    }
+}
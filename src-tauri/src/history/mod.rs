@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::games::Platform;
+
+mod db;
+pub mod service;
+
+pub use service::HistoryService;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryStatus {
+    Running,
+    Success,
+    Failed,
+    Cancelled,
+    Paused,
+    // Приложение закрылось во время обновления
+    Interrupted,
+}
+
+impl HistoryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Success => "success",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+            Self::Paused => "paused",
+            Self::Interrupted => "interrupted",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "running" => Some(Self::Running),
+            "success" => Some(Self::Success),
+            "failed" => Some(Self::Failed),
+            "cancelled" => Some(Self::Cancelled),
+            "paused" => Some(Self::Paused),
+            "interrupted" => Some(Self::Interrupted),
+            _ => None,
+        }
+    }
+}
+
+// Один запуск обновления. Возобновление после паузы - новый запуск
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub game_id: String,
+    pub game_name: String,
+    pub platform: Platform,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub bytes_downloaded: Option<u64>,
+    pub old_build_id: Option<u32>,
+    pub new_build_id: Option<u32>,
+}
+
+// Итог запуска, записывается по его завершении
+#[derive(Debug, Clone)]
+pub struct HistoryOutcome {
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub bytes_downloaded: Option<u64>,
+    pub new_build_id: Option<u32>,
+}
+
+// Фильтр get_update_history; границы from и to относятся к началу запуска
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub game_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub status: Option<HistoryStatus>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    // Всего записей под фильтром, без учёта limit и offset
+    pub total: u64,
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Sqlite};
+use crate::error::Result;
+use crate::games::Game;
+use super::{HistoryOutcome, HistoryPage, HistoryQuery, db};
+
+// История обновлений в settings.db. Запись создаётся при старте
+// обновления и дополняется итогом, когда оно завершится
+#[derive(Clone)]
+pub struct HistoryService {
+    pool: Arc<Pool<Sqlite>>,
+}
+
+impl HistoryService {
+    pub async fn new(pool: Arc<Pool<Sqlite>>) -> Result<Self> {
+        db::init_history(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    // Возвращает id записи для finish
+    pub async fn start(&self, game: &Game) -> Result<i64> {
+        db::insert_started(&self.pool, game).await
+    }
+
+    pub async fn finish(&self, id: i64, outcome: &HistoryOutcome) -> Result<()> {
+        db::finish(&self.pool, id, outcome).await
+    }
+
+    pub async fn query(&self, query: &HistoryQuery) -> Result<HistoryPage> {
+        db::query(&self.pool, query).await
+    }
+
+    pub async fn last_updates(&self) -> Result<HashMap<String, DateTime<Utc>>> {
+        db::last_updates(&self.pool).await
+    }
+}
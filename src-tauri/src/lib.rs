@@ -3,13 +3,24 @@ mod cache;
 mod error;
 pub mod registry;
 pub mod games;
+pub mod history;
 pub mod settings;
 pub mod commands;
 pub mod logging;
+pub mod migrations;
+mod process;
+pub mod queue;
+pub mod scheduler;
+pub mod secrets;
+pub mod steam;
+pub mod vdf;
 
 use parking_lot::Mutex;
 use crate::settings::manager::SettingsManager;
 use crate::games::manager::GameManager;
+use crate::history::HistoryService;
+use crate::queue::QueueManager;
+use crate::scheduler::Scheduler;
 use crate::logging::{Logger, LogConfig, LogRotation};
 use tauri::Manager;
 use std::sync::Arc;
@@ -71,11 +82,39 @@ pub fn run() {
             let settings = runtime_clone
                 .block_on(async { settings_manager.load().await })
                 .expect("Failed to load settings");
+            let settings = settings_manager.with_password(settings);
 
-            let game_manager = GameManager::new(settings, app.handle().clone());
+            let history = runtime_clone
+                .block_on(HistoryService::new(settings_manager.pool()))
+                .expect("Failed to initialize update history");
+
+            let game_manager = GameManager::new(settings.clone(), history.clone(), app.handle().clone());
+
+            let queue_manager = runtime_clone
+                .block_on(QueueManager::new(
+                    settings_manager.pool(),
+                    game_manager.clone(),
+                    app.handle().clone(),
+                ))
+                .expect("Failed to initialize update queue");
+            queue_manager.start();
+
+            let scheduler = runtime_clone
+                .block_on(Scheduler::new(
+                    settings_manager.pool(),
+                    &settings,
+                    game_manager.clone(),
+                    queue_manager.clone(),
+                    app.handle().clone(),
+                ))
+                .expect("Failed to initialize scheduler");
+            scheduler.start(&runtime_clone);
 
             app.manage(settings_manager.clone());
             app.manage(game_manager.clone());
+            app.manage(history);
+            app.manage(queue_manager);
+            app.manage(scheduler);
 
             app.manage(AppState::new(
                 settings_manager,
@@ -95,9 +134,23 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::get_settings,
             commands::save_settings,
+            commands::update_settings,
+            commands::get_scheduler_status,
+            commands::check_updates_now,
             commands::get_installed_games,
             commands::check_game_updates,
+            commands::compare_game_builds,
+            commands::submit_steam_guard_code,
             commands::update_game,
+            commands::cancel_update,
+            commands::pause_update,
+            commands::resume_update,
+            commands::close_game,
+            commands::get_update_history,
+            commands::enqueue_update,
+            commands::list_queue,
+            commands::reorder_queue,
+            commands::remove_from_queue,
             commands::select_directory,
             commands::refresh_games_list
         ])
@@ -1,3 +1,7 @@
+mod redact;
+
+pub use redact::{redact, register_secret, RedactingMakeWriter};
+
 use std::path::PathBuf;
 use std::sync::Arc;
 use chrono::Local;
@@ -14,6 +18,7 @@ use tracing_subscriber::{
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LogConfig {
     pub level: String,
     pub file_name: String,
@@ -99,6 +104,8 @@ impl Logger {
             _ => Level::INFO,
         };
 
+        // Оба слоя пишут через RedactingMakeWriter: пароли, токены и коды
+        // Steam Guard маскируются до попадания в консоль и файл
         // Create file layer
         let file_layer = fmt::layer()
             .with_file(true)
@@ -107,7 +114,7 @@ impl Logger {
             .with_thread_names(true)
             .with_target(true)
             .with_timer(LocalTimer)
-            .with_writer(file_appender)
+            .with_writer(RedactingMakeWriter::new(file_appender))
             .with_filter(tracing_subscriber::filter::LevelFilter::from_level(level));
 
         // Create console layer
@@ -118,6 +125,7 @@ impl Logger {
             .with_thread_names(true)
             .with_target(true)
             .with_timer(LocalTimer)
+            .with_writer(RedactingMakeWriter::new(std::io::stdout))
             .with_filter(tracing_subscriber::filter::LevelFilter::from_level(level));
 
         // Combine layers and set as global default
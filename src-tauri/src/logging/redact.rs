@@ -0,0 +1,248 @@
+use parking_lot::RwLock;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use tracing_subscriber::fmt::MakeWriter;
+
+const MASK: &str = "***";
+// Короткие значения маскировать нельзя: они встречаются в обычном тексте
+const MIN_SECRET_LEN: usize = 3;
+const SECRET_KEYS: &[&str] = &["password", "passwd", "passphrase", "token", "secret", "authcode", "guardcode"];
+
+lazy_static::lazy_static! {
+    static ref SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+}
+
+// Значение, которое не должно попасть ни в лог, ни во фронтенд: имя
+// пользователя, пароль, код Steam Guard
+pub fn register_secret(value: &str) {
+    let value = value.trim();
+    if value.len() < MIN_SECRET_LEN || value.eq_ignore_ascii_case("anonymous") {
+        return;
+    }
+
+    let mut secrets = SECRETS.write();
+    if !secrets.iter().any(|s| s == value) {
+        secrets.push(value.to_string());
+        // Длинные первыми, чтобы не оставлять хвосты от значений-префиксов
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+}
+
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut result = Cow::Borrowed(text);
+
+    for secret in SECRETS.read().iter() {
+        if result.contains(secret.as_str()) {
+            result = Cow::Owned(result.replace(secret.as_str(), MASK));
+        }
+    }
+
+    match redact_patterns(&result) {
+        Some(masked) => Cow::Owned(masked),
+        None => result,
+    }
+}
+
+// Секреты по виду: аргументы команды steamcmd "login <user> <password> [<code>]",
+// имя в "Logging in user '<user>'", "password: x", "token=x", а также JWT
+// и длинные hex-строки
+fn redact_patterns(text: &str) -> Option<String> {
+    let words = words(text);
+    let mut masked = Vec::new();
+
+    for (i, &(start, end)) in words.iter().enumerate() {
+        let word = &text[start..end];
+        let lower = word.to_ascii_lowercase();
+        let next = words.get(i + 1).copied();
+
+        if is_login_command(text, start, &lower) {
+            // В командной строке аргументы заканчиваются на следующей +команде
+            let args: Vec<_> = words[i + 1..]
+                .iter()
+                .take(3)
+                .take_while(|&&(s, _)| !text[s..].starts_with('+'))
+                .copied()
+                .collect();
+            if args.first().is_some_and(|&(s, e)| !text[s..e].eq_ignore_ascii_case("anonymous")) {
+                masked.extend(args);
+            }
+        } else if lower == "user" {
+            // Имя в кавычках: 'gaben'
+            if let Some((s, e)) = next.filter(|&(s, e)| e - s > 2 && text[s..e].starts_with('\'') && text[s..e].ends_with('\'')) {
+                masked.push((s + 1, e - 1));
+            }
+        } else if let Some((key, value)) = lower.split_once('=') {
+            if is_secret_key(key) && !value.is_empty() {
+                masked.push((start + key.len() + 1, end));
+            }
+        } else if let Some(key) = lower.strip_suffix(':') {
+            if is_secret_key(key) {
+                masked.extend(next);
+            }
+        } else if looks_like_token(word) {
+            masked.push((start, end));
+        }
+    }
+
+    if masked.is_empty() {
+        return None;
+    }
+
+    masked.sort_unstable();
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (start, end) in masked {
+        if start < pos {
+            continue;
+        }
+        result.push_str(&text[pos..start]);
+        result.push_str(MASK);
+        pos = end;
+    }
+    result.push_str(&text[pos..]);
+    Some(result)
+}
+
+// Команда входа, а не слово "login" в тексте: аргумент +login командной
+// строки steamcmd, начало строки или ввод после приглашения Steam>
+fn is_login_command(text: &str, start: usize, word: &str) -> bool {
+    let before = text[..start].trim_end_matches([' ', '\t']);
+    let line_start = before.is_empty() || before.ends_with('\n');
+    word == "+login" || word.ends_with("steam>login") || (word == "login" && line_start)
+}
+
+// Границы слов без окружающих кавычек и знаков препинания
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        let separator = c.is_whitespace() || matches!(c, '"' | ',' | ';' | '(' | ')');
+        match (start, separator) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    words
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key: String = key.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    SECRET_KEYS.iter().any(|k| key.ends_with(k))
+}
+
+fn looks_like_token(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| matches!(c, '\'' | '.' | ':'));
+    let jwt = word.starts_with("eyJ") && word.len() > 20;
+    let hex = word.len() >= 32 && word.chars().all(|c| c.is_ascii_hexdigit());
+    jwt || hex
+}
+
+// Обёртка над writer'ом слоя fmt: событие накапливается целиком
+// и маскируется перед записью в консоль или файл
+pub struct RedactingMakeWriter<M> {
+    inner: M,
+}
+
+impl<M> RedactingMakeWriter<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+pub struct RedactingWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let text = String::from_utf8_lossy(&self.buffer);
+            self.inner.write_all(redact(&text).as_bytes())?;
+            self.buffer.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for RedactingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn username_is_masked() {
+        let line = "Logging in user 'gaben' to Steam Public...FAILED (Invalid Password)";
+        assert_eq!(redact(line), "Logging in user '***' to Steam Public...FAILED (Invalid Password)");
+
+        register_secret("gordon_f");
+        assert_eq!(redact("Steam Guard code required for gordon_f"), "Steam Guard code required for ***");
+    }
+
+    #[test]
+    fn login_command_masks_all_arguments() {
+        assert_eq!(redact("login gaben hunter22"), "login *** ***");
+        assert_eq!(redact("login gaben hunter22 X7K2P"), "login *** *** ***");
+        assert_eq!(redact("Steam>login gaben hunter22"), "Steam>login *** ***");
+        assert_eq!(
+            redact("steamcmd +login gaben hunter22 +app_update 570 +quit"),
+            "steamcmd +login *** *** +app_update 570 +quit"
+        );
+        assert_eq!(redact("first line\n  login gaben"), "first line\n  login ***");
+        assert_eq!(redact("login anonymous"), "login anonymous");
+    }
+
+    #[test]
+    fn login_in_text_is_not_masked() {
+        for line in [
+            "Failed to login to Steam: Invalid Password",
+            "steamcmd: login failed, retrying in 5 seconds",
+            "Update state (0x61) downloading",
+        ] {
+            assert_eq!(redact(line), line);
+        }
+    }
+
+    #[test]
+    fn registered_password_is_masked() {
+        register_secret("correct-horse-staple");
+        assert_eq!(
+            redact("steamcmd exited: bad password correct-horse-staple"),
+            "steamcmd exited: bad password ***"
+        );
+    }
+
+    #[test]
+    fn secret_keys_and_tokens_are_masked() {
+        assert_eq!(redact("password: qwerty123"), "password: ***");
+        assert_eq!(redact("refresh_token=abc.def"), "refresh_token=***");
+        assert_eq!(redact("key 0123456789abcdef0123456789abcdef"), "key ***");
+    }
+}
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS update_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id TEXT NOT NULL UNIQUE,
+    platform TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    position INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    enqueued_at INTEGER NOT NULL
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS update_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id TEXT NOT NULL,
+    game_name TEXT NOT NULL,
+    platform TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    status TEXT NOT NULL,
+    error TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    bytes_downloaded INTEGER,
+    old_build_id INTEGER,
+    new_build_id INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS update_history_game ON update_history (game_id, started_at);
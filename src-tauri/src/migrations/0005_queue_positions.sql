@@ -0,0 +1,8 @@
+-- Очередь упорядочена только по position: приоритет задаёт место при
+-- постановке. Существующие записи получают позиции в прежнем порядке
+-- (priority DESC, position ASC)
+UPDATE update_queue SET position = (
+    SELECT COUNT(*) FROM update_queue AS other
+    WHERE other.priority > update_queue.priority
+       OR (other.priority = update_queue.priority AND other.position <= update_queue.position)
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS scheduler_state (
+    key TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
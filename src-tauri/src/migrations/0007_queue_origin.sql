@@ -0,0 +1,3 @@
+-- Кто поставил запись: пользователь (manual) или планировщик (auto).
+-- Автоматические записи снимаются и приостанавливаются при закрытии окна
+ALTER TABLE update_queue ADD COLUMN origin TEXT NOT NULL DEFAULT 'manual';
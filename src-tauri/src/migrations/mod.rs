@@ -0,0 +1,177 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use chrono::Utc;
+use sqlx::{Pool, Row, Sqlite, SqliteConnection};
+use crate::error::{Result, Error};
+use crate::settings;
+use crate::{log_error, log_info, log_warn};
+
+pub type MigrationFuture<'c> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'c>>;
+
+pub enum Step {
+    Sql(&'static str),
+    // Для преобразований данных, которые не выразить одним SQL
+    Rust(for<'c> fn(&'c mut SqliteConnection) -> MigrationFuture<'c>),
+}
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub step: Step,
+}
+
+// Версии только растут; применённую миграцию не меняют, а добавляют новую.
+// Первые миграции повторяют таблицы, которые раньше создавались через
+// CREATE TABLE IF NOT EXISTS, поэтому на старых базах ничего не меняют
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "settings",
+        step: Step::Sql(include_str!("0001_settings.sql")),
+    },
+    Migration {
+        version: 2,
+        name: "update_queue",
+        step: Step::Sql(include_str!("0002_update_queue.sql")),
+    },
+    Migration {
+        version: 3,
+        name: "update_history",
+        step: Step::Sql(include_str!("0003_update_history.sql")),
+    },
+    Migration {
+        version: 4,
+        name: "typed_settings",
+        step: Step::Rust(settings::convert_legacy_settings),
+    },
+    Migration {
+        version: 5,
+        name: "queue_positions",
+        step: Step::Sql(include_str!("0005_queue_positions.sql")),
+    },
+    Migration {
+        version: 6,
+        name: "scheduler_state",
+        step: Step::Sql(include_str!("0006_scheduler_state.sql")),
+    },
+    Migration {
+        version: 7,
+        name: "queue_origin",
+        step: Step::Sql(include_str!("0007_queue_origin.sql")),
+    },
+];
+
+// Применяет недостающие миграции в одной транзакции: при ошибке база
+// остаётся в прежней версии. Перед миграцией существующей базы рядом
+// сохраняется её копия
+pub async fn run(pool: &Pool<Sqlite>, db_path: &Path) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    let current = current_version(pool).await?;
+    let latest = MIGRATIONS.last().map_or(0, |migration| migration.version);
+    if current > latest {
+        log_warn!(
+            "Database schema version {} is newer than supported {}, skipping migrations",
+            current, latest
+        );
+        return Ok(());
+    }
+
+    let pending: Vec<_> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if has_user_tables(pool).await? {
+        backup(pool, db_path, current).await?;
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    for migration in pending {
+        log_info!("Applying database migration {} ({})", migration.version, migration.name);
+
+        let result = match &migration.step {
+            Step::Sql(sql) => sqlx::raw_sql(sql)
+                .execute(&mut *tx)
+                .await
+                .map(|_| ())
+                .map_err(|e| Error::Database(e.to_string())),
+            Step::Rust(apply) => apply(&mut tx).await,
+        };
+        if let Err(e) = result {
+            log_error!("Migration {} ({}) failed: {}", migration.version, migration.name, e);
+            return Err(e);
+        }
+
+        sqlx::query("INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(Utc::now().timestamp())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::Database(e.to_string()))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    log_info!("Database schema migrated from version {} to {}", current, latest);
+    Ok(())
+}
+
+async fn current_version(pool: &Pool<Sqlite>) -> Result<i64> {
+    let row = sqlx::query("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(row.get(0))
+}
+
+// Пустую базу, созданную при первом запуске, копировать незачем
+async fn has_user_tables(pool: &Pool<Sqlite>) -> Result<bool> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) FROM sqlite_master
+         WHERE type = 'table' AND name NOT IN ('schema_version', 'sqlite_sequence')",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(row.get::<i64, _>(0) > 0)
+}
+
+// VACUUM INTO даёт согласованную копию, даже если база открыта
+async fn backup(pool: &Pool<Sqlite>, db_path: &Path, version: i64) -> Result<()> {
+    let backup_path = db_path.with_extension(format!("v{}.bak", version));
+    if backup_path.exists() {
+        std::fs::remove_file(&backup_path)
+            .map_err(|e| Error::Database(format!("Failed to remove old database backup: {}", e)))?;
+    }
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(backup_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| Error::Database(format!("Failed to back up database: {}", e)))?;
+
+    log_info!("Database backed up to {}", backup_path.display());
+    Ok(())
+}
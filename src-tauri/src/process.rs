@@ -0,0 +1,350 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use tokio::process::{Child, Command};
+use tracing::debug;
+
+// steamcmd.sh и лаунчер Epic запускают собственные дочерние процессы.
+// На unix процесс становится лидером новой группы, чтобы её можно было
+// завершить целиком
+pub fn spawn_group(cmd: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+// Завершает процесс вместе с потомками и дожидается его выхода
+pub async fn kill_tree(child: &mut Child) -> io::Result<()> {
+    if let Some(pid) = child.id() {
+        kill_descendants(pid).await;
+    }
+    child.kill().await
+}
+
+#[cfg(unix)]
+async fn kill_descendants(pid: u32) {
+    // Группа совпадает с pid лидера, см. spawn_group
+    let result = unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+    if result != 0 {
+        debug!("killpg({}) failed: {}", pid, io::Error::last_os_error());
+    }
+}
+
+#[cfg(windows)]
+async fn kill_descendants(pid: u32) {
+    let result = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .output()
+        .await;
+    if let Err(e) = result {
+        debug!("taskkill /T for {} failed: {}", pid, e);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn kill_descendants(_pid: u32) {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningProcess {
+    pub pid: u32,
+    pub name: String,
+    // Исполняемый файл, а под Wine/Proton - путь к .exe из командной строки
+    pub path: PathBuf,
+}
+
+// Процессы, запущенные из каталога игры
+pub fn processes_under(dir: &Path) -> Vec<RunningProcess> {
+    running_under(&list_processes(), dir).cloned().collect()
+}
+
+// Для проверки нескольких каталогов по одному снимку процессов
+pub fn running_under<'a>(
+    processes: &'a [RunningProcess],
+    dir: &Path,
+) -> impl Iterator<Item = &'a RunningProcess> {
+    let dir = normalize_dir(dir);
+    processes
+        .iter()
+        .filter(move |process| path_starts_with(&process.path, &dir))
+}
+
+// Просит процесс завершиться; игра получает возможность сохраниться
+pub async fn terminate(pid: u32) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let result = unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let output = Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T"])
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    let _ = pid;
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn list_processes() -> Vec<RunningProcess> {
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("Failed to read /proc: {}", e);
+            return Vec::new();
+        }
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            // Чужие процессы недоступны без прав, их пропускаем
+            let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+            let exe = PathBuf::from(exe.to_string_lossy().trim_end_matches(" (deleted)"));
+            let is_wine = exe
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("wine"));
+            let path = match is_wine {
+                true => wine_executable(pid).unwrap_or(exe),
+                false => exe,
+            };
+            Some(RunningProcess {
+                pid,
+                name: path.file_name()?.to_string_lossy().into_owned(),
+                path,
+            })
+        })
+        .collect()
+}
+
+// Под Wine исполняемый файл процесса - wine-preloader, сама игра видна
+// только в аргументах
+#[cfg(target_os = "linux")]
+fn wine_executable(pid: u32) -> Option<PathBuf> {
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    wine_exe_path(&cmdline)
+}
+
+// Аргументы в /proc/<pid>/cmdline разделены нулевыми байтами
+#[cfg(target_os = "linux")]
+fn wine_exe_path(cmdline: &[u8]) -> Option<PathBuf> {
+    cmdline
+        .split(|&byte| byte == 0)
+        .map(|arg| String::from_utf8_lossy(arg).replace('\\', "/"))
+        .find(|arg| arg.to_ascii_lowercase().ends_with(".exe"))
+        .map(|arg| match arg.get(..2) {
+            // Диск Z: в префиксе Wine - корень файловой системы
+            Some("Z:") | Some("z:") => PathBuf::from(&arg[2..]),
+            _ => PathBuf::from(arg),
+        })
+        .filter(|path| path.is_absolute())
+}
+
+#[cfg(windows)]
+pub fn list_processes() -> Vec<RunningProcess> {
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    let mut processes = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            debug!("CreateToolhelp32Snapshot failed: {}", io::Error::last_os_error());
+            return processes;
+        }
+
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+
+        let mut ok = Process32FirstW(snapshot, &mut entry);
+        while ok != 0 {
+            let pid = entry.th32ProcessID;
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            // Системные процессы открыть нельзя, они нас и не интересуют
+            if !handle.is_null() {
+                let mut buffer = [0u16; 1024];
+                let mut len = buffer.len() as u32;
+                if QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len) != 0 {
+                    let path = PathBuf::from(std::ffi::OsString::from_wide(&buffer[..len as usize]));
+                    let name_len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+                    processes.push(RunningProcess {
+                        pid,
+                        name: String::from_utf16_lossy(&entry.szExeFile[..name_len]),
+                        path,
+                    });
+                }
+                CloseHandle(handle);
+            }
+            ok = Process32NextW(snapshot, &mut entry);
+        }
+
+        CloseHandle(snapshot);
+    }
+    processes
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn list_processes() -> Vec<RunningProcess> {
+    Vec::new()
+}
+
+// canonicalize на Windows возвращает путь вида \\?\C:\..., который не совпадёт
+// с путями процессов, поэтому каталог используется как есть
+#[cfg(windows)]
+fn normalize_dir(dir: &Path) -> PathBuf {
+    dir.to_path_buf()
+}
+
+#[cfg(not(windows))]
+fn normalize_dir(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
+
+// Пути Windows сравниваются без учёта регистра
+#[cfg(windows)]
+fn path_starts_with(path: &Path, dir: &Path) -> bool {
+    let path = path.to_string_lossy().to_lowercase().replace('/', "\\");
+    let dir = dir.to_string_lossy().to_lowercase().replace('/', "\\");
+    let dir = dir.trim_end_matches('\\');
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+}
+
+#[cfg(not(windows))]
+fn path_starts_with(path: &Path, dir: &Path) -> bool {
+    path.starts_with(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Процесс завершён; зомби, которого ещё не забрал init, тоже считается завершённым
+    #[cfg(target_os = "linux")]
+    fn is_alive(pid: u32) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .ok()
+            .and_then(|stat| stat.rsplit_once(')').map(|(_, rest)| rest.trim_start().starts_with('Z')))
+            .is_some_and(|zombie| !zombie)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn kill_tree_kills_whole_group() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 60 & echo $!; sleep 60"]);
+        let mut child = spawn_group(&mut cmd)
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        stdout.read_line(&mut line).await.unwrap();
+        let background: u32 = line.trim().parse().unwrap();
+        let shell = child.id().unwrap();
+        assert!(is_alive(shell) && is_alive(background));
+
+        kill_tree(&mut child).await.unwrap();
+
+        // Фоновый процесс забирает init, это может занять немного времени
+        for _ in 0..50 {
+            if !is_alive(background) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert!(!is_alive(shell));
+        assert!(!is_alive(background));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn wine_exe_path_maps_drive_z_to_root() {
+        let cmdline = b"/usr/bin/wine64-preloader\0Z:\\games\\Dota 2\\game\\dota2.exe\0-novid\0";
+        assert_eq!(wine_exe_path(cmdline), Some(PathBuf::from("/games/Dota 2/game/dota2.exe")));
+
+        let lowercase = b"wine\0z:/games/Game.EXE\0";
+        assert_eq!(wine_exe_path(lowercase), Some(PathBuf::from("/games/Game.EXE")));
+    }
+
+    // Другие диски префикса на файловую систему не отображаются
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn wine_exe_path_ignores_other_drives() {
+        assert_eq!(wine_exe_path(b"wine\0C:\\Games\\game.exe\0"), None);
+        assert_eq!(wine_exe_path(b"wine\0game.exe\0"), None);
+        assert_eq!(wine_exe_path(b"wine\0--version\0"), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn path_starts_with_compares_components() {
+        let dir = Path::new("/games/Dota 2");
+        assert!(path_starts_with(Path::new("/games/Dota 2/game/dota2"), dir));
+        assert!(path_starts_with(Path::new("/games/Dota 2"), dir));
+        assert!(!path_starts_with(Path::new("/games/Dota 2 Test/dota2"), dir));
+        assert!(!path_starts_with(Path::new("/games/dota 2/game/dota2"), dir));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn path_starts_with_ignores_case_and_separators() {
+        let dir = Path::new("C:\\Games\\Dota 2\\");
+        assert!(path_starts_with(Path::new("c:\\games\\dota 2\\game\\dota2.exe"), dir));
+        assert!(path_starts_with(Path::new("C:/Games/Dota 2/game/dota2.exe"), dir));
+        assert!(path_starts_with(Path::new("C:\\Games\\Dota 2"), dir));
+        assert!(!path_starts_with(Path::new("C:\\Games\\Dota 2 Test\\dota2.exe"), dir));
+    }
+
+    // Копия sleep в отдельном каталоге изображает запущенную игру
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn running_under_finds_process_from_directory() {
+        let dir = std::env::temp_dir().join(format!("updateio-process-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("game");
+        std::fs::copy("/bin/sleep", &exe).unwrap();
+
+        let mut child = Command::new(&exe).arg("60").kill_on_drop(true).spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        // До exec процесс ещё выполняет образ родителя
+        let mut found = Vec::new();
+        for _ in 0..50 {
+            found = running_under(&list_processes(), &dir).map(|p| p.pid).collect();
+            if !found.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let other = dir.with_file_name(format!("updateio-process-other-{}", std::process::id()));
+        let outside = running_under(&list_processes(), &other).count();
+
+        child.kill().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found, vec![pid]);
+        assert_eq!(outside, 0);
+    }
+}
@@ -0,0 +1,319 @@
+use sqlx::{Pool, Sqlite, SqliteConnection, Row};
+use sqlx::sqlite::SqliteRow;
+use chrono::{DateTime, Utc};
+use crate::error::{Result, Error};
+use crate::games::Platform;
+use super::{QueueEntry, QueueOrigin, QueueStatus};
+
+// Таблица создаётся миграцией, см. migrations
+pub async fn init_queue(pool: &Pool<Sqlite>) -> Result<()> {
+    // Обновления, прерванные закрытием приложения, запускаются заново
+    sqlx::query("UPDATE update_queue SET status = 'queued' WHERE status = 'running'")
+        .execute(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+pub async fn list_queue(pool: &Pool<Sqlite>) -> Result<Vec<QueueEntry>> {
+    let rows = sqlx::query(
+        "SELECT id, game_id, platform, priority, position, status, origin, enqueued_at
+         FROM update_queue ORDER BY position ASC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    rows.iter().map(entry_from_row).collect()
+}
+
+// Новая запись встаёт перед первой записью с меньшим приоритетом.
+// Повторная постановка той же игры не создаёт дубль: более высокий приоритет
+// передвигает запись вперёд, приостановленная запись снова ждёт запуска.
+// Ручная постановка делает запись ручной, автоматическая её не меняет
+pub async fn enqueue(
+    pool: &Pool<Sqlite>,
+    game_id: &str,
+    platform: Platform,
+    priority: i64,
+    origin: QueueOrigin,
+) -> Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    let rows = sqlx::query("SELECT game_id, priority FROM update_queue ORDER BY position ASC")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+    let mut order: Vec<(String, i64)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+    let existing = order.iter().position(|(id, _)| id == game_id);
+    let raised = match existing {
+        Some(index) => {
+            let raised = priority > order[index].1;
+            if raised {
+                order.remove(index);
+            }
+            raised
+        }
+        None => true,
+    };
+
+    if raised {
+        let index = order
+            .iter()
+            .position(|(_, other)| *other < priority)
+            .unwrap_or(order.len());
+        order.insert(index, (game_id.to_string(), priority));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO update_queue (game_id, platform, priority, position, status, origin, enqueued_at)
+        VALUES (?, ?, ?, 0, 'queued', ?, ?)
+        ON CONFLICT(game_id) DO UPDATE SET
+            priority = MAX(priority, excluded.priority),
+            status = CASE status WHEN 'paused' THEN 'queued' ELSE status END,
+            origin = CASE excluded.origin WHEN 'manual' THEN 'manual' ELSE origin END
+        "#,
+    )
+    .bind(game_id)
+    .bind(platform.as_str())
+    .bind(priority)
+    .bind(origin_name(origin))
+    .bind(Utc::now().timestamp())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    if raised {
+        let ids: Vec<&String> = order.iter().map(|(id, _)| id).collect();
+        renumber(&mut tx, &ids).await?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+pub async fn set_status(pool: &Pool<Sqlite>, game_id: &str, status: QueueStatus) -> Result<()> {
+    sqlx::query("UPDATE update_queue SET status = ? WHERE game_id = ?")
+        .bind(status_name(status))
+        .bind(game_id)
+        .execute(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+pub async fn remove(pool: &Pool<Sqlite>, game_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM update_queue WHERE game_id = ?")
+        .bind(game_id)
+        .execute(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Перечисленные игры ставятся в начало в заданном порядке, остальные
+// сохраняют свой относительный порядок после них
+pub async fn reorder(pool: &Pool<Sqlite>, game_ids: &[String]) -> Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    let rows = sqlx::query("SELECT game_id FROM update_queue ORDER BY position ASC")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+    let current: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+
+    let ordered = game_ids
+        .iter()
+        .filter(|id| current.contains(id))
+        .chain(current.iter().filter(|id| !game_ids.contains(id)));
+
+    renumber(&mut tx, &ordered.collect::<Vec<_>>()).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+// Позиции 1..n в заданном порядке
+async fn renumber(tx: &mut SqliteConnection, game_ids: &[&String]) -> Result<()> {
+    for (position, game_id) in game_ids.iter().enumerate() {
+        sqlx::query("UPDATE update_queue SET position = ? WHERE game_id = ?")
+            .bind(position as i64 + 1)
+            .bind(game_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::Database(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn entry_from_row(row: &SqliteRow) -> Result<QueueEntry> {
+    let platform: String = row.get("platform");
+    let status: String = row.get("status");
+    let origin: String = row.get("origin");
+
+    Ok(QueueEntry {
+        id: row.get("id"),
+        game_id: row.get("game_id"),
+        platform: Platform::from_name(&platform)
+            .ok_or_else(|| Error::Database(format!("Unknown platform in queue: {}", platform)))?,
+        priority: row.get("priority"),
+        position: row.get("position"),
+        status: match status.as_str() {
+            "running" => QueueStatus::Running,
+            "paused" => QueueStatus::Paused,
+            _ => QueueStatus::Queued,
+        },
+        origin: match origin.as_str() {
+            "auto" => QueueOrigin::Auto,
+            _ => QueueOrigin::Manual,
+        },
+        enqueued_at: DateTime::from_timestamp(row.get("enqueued_at"), 0),
+    })
+}
+
+fn origin_name(origin: QueueOrigin) -> &'static str {
+    match origin {
+        QueueOrigin::Manual => "manual",
+        QueueOrigin::Auto => "auto",
+    }
+}
+
+fn status_name(status: QueueStatus) -> &'static str {
+    match status {
+        QueueStatus::Queued => "queued",
+        QueueStatus::Running => "running",
+        QueueStatus::Paused => "paused",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use super::*;
+
+    async fn pool() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::migrations::run(&pool, Path::new("memory.db")).await.unwrap();
+        pool
+    }
+
+    async fn order(pool: &Pool<Sqlite>) -> Vec<String> {
+        list_queue(pool).await.unwrap().into_iter().map(|entry| entry.game_id).collect()
+    }
+
+    #[tokio::test]
+    async fn priority_sets_insert_position() {
+        let pool = pool().await;
+        enqueue(&pool, "a", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        enqueue(&pool, "b", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        enqueue(&pool, "c", Platform::Steam, 5, QueueOrigin::Manual).await.unwrap();
+        enqueue(&pool, "d", Platform::Epic, 5, QueueOrigin::Manual).await.unwrap();
+        assert_eq!(order(&pool).await, ["c", "d", "a", "b"]);
+
+        // Повышение приоритета передвигает запись, понижение не меняет ничего
+        enqueue(&pool, "b", Platform::Steam, 3, QueueOrigin::Manual).await.unwrap();
+        enqueue(&pool, "c", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        assert_eq!(order(&pool).await, ["c", "d", "b", "a"]);
+
+        let positions: Vec<_> = list_queue(&pool).await.unwrap().iter().map(|entry| entry.position).collect();
+        assert_eq!(positions, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn reorder_wins_over_priority() {
+        let pool = pool().await;
+        enqueue(&pool, "low", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        enqueue(&pool, "high", Platform::Steam, 10, QueueOrigin::Manual).await.unwrap();
+        assert_eq!(order(&pool).await, ["high", "low"]);
+
+        reorder(&pool, &["low".to_string()]).await.unwrap();
+        assert_eq!(order(&pool).await, ["low", "high"]);
+    }
+
+    #[tokio::test]
+    async fn paused_entry_is_kept_and_requeued() {
+        let pool = pool().await;
+        enqueue(&pool, "a", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        set_status(&pool, "a", QueueStatus::Paused).await.unwrap();
+
+        init_queue(&pool).await.unwrap();
+        assert_eq!(list_queue(&pool).await.unwrap()[0].status, QueueStatus::Paused);
+
+        enqueue(&pool, "a", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        let entries = list_queue(&pool).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].status, QueueStatus::Queued);
+    }
+
+    #[tokio::test]
+    async fn manual_enqueue_overrides_auto_origin() {
+        let pool = pool().await;
+        enqueue(&pool, "a", Platform::Steam, 0, QueueOrigin::Auto).await.unwrap();
+        enqueue(&pool, "b", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        enqueue(&pool, "b", Platform::Steam, 0, QueueOrigin::Auto).await.unwrap();
+
+        let origins: Vec<_> = list_queue(&pool).await.unwrap().iter().map(|entry| entry.origin).collect();
+        assert_eq!(origins, [QueueOrigin::Auto, QueueOrigin::Manual]);
+
+        enqueue(&pool, "a", Platform::Steam, 0, QueueOrigin::Manual).await.unwrap();
+        assert_eq!(list_queue(&pool).await.unwrap()[0].origin, QueueOrigin::Manual);
+    }
+
+    #[tokio::test]
+    async fn migration_keeps_priority_order() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::raw_sql(include_str!("../migrations/0002_update_queue.sql"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (game_id, priority, position) in [("a", 0, 1), ("b", 5, 2), ("c", 0, 3), ("d", 5, 4)] {
+            sqlx::query(
+                "INSERT INTO update_queue (game_id, platform, priority, position, status, enqueued_at)
+                 VALUES (?, 'steam', ?, ?, 'queued', 0)",
+            )
+            .bind(game_id)
+            .bind(priority)
+            .bind(position)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        for sql in [
+            include_str!("../migrations/0005_queue_positions.sql"),
+            include_str!("../migrations/0007_queue_origin.sql"),
+        ] {
+            sqlx::raw_sql(sql).execute(&pool).await.unwrap();
+        }
+        assert_eq!(order(&pool).await, ["b", "d", "a", "c"]);
+        // Записи, созданные до появления origin, считаются ручными
+        assert!(list_queue(&pool).await.unwrap().iter().all(|entry| entry.origin == QueueOrigin::Manual));
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use sqlx::{Pool, Sqlite};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+use crate::error::{Error, Result};
+use crate::games::GameManager;
+use crate::{log_error, log_info, log_warn};
+use super::{QueueEntry, QueueLimits, QueueOrigin, QueueStatus, db};
+
+// Очередь обновлений в settings.db. Диспетчер запускает записи по
+// приоритету, не превышая лимит одновременных обновлений платформы
+#[derive(Clone)]
+pub struct QueueManager {
+    pool: Arc<Pool<Sqlite>>,
+    games: GameManager,
+    app: Arc<AppHandle>,
+    limits: QueueLimits,
+    wake: Arc<Notify>,
+}
+
+impl QueueManager {
+    pub async fn new(pool: Arc<Pool<Sqlite>>, games: GameManager, app: AppHandle) -> Result<Self> {
+        db::init_queue(&pool).await?;
+
+        Ok(Self {
+            pool,
+            games,
+            app: Arc::new(app),
+            limits: QueueLimits::default(),
+            wake: Arc::new(Notify::new()),
+        })
+    }
+
+    // Запускает диспетчер; записи, оставшиеся с прошлого запуска, начнут выполняться сразу
+    pub fn start(&self) {
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                if let Err(e) = manager.dispatch().await {
+                    log_error!("Update queue dispatch failed: {}", e);
+                }
+                manager.wake.notified().await;
+            }
+        });
+    }
+
+    pub async fn enqueue(&self, game_id: &str, priority: i64, origin: QueueOrigin) -> Result<Vec<QueueEntry>> {
+        let game = self.games.find_game(game_id).await?;
+        db::enqueue(&self.pool, game_id, game.platform, priority, origin).await?;
+        log_info!("Game {} added to update queue with priority {} ({:?})", game_id, priority, origin);
+        self.changed().await
+    }
+
+    pub async fn list(&self) -> Result<Vec<QueueEntry>> {
+        db::list_queue(&self.pool).await
+    }
+
+    pub async fn reorder(&self, game_ids: &[String]) -> Result<Vec<QueueEntry>> {
+        db::reorder(&self.pool, game_ids).await?;
+        self.changed().await
+    }
+
+    // Выполняющееся обновление отменяется, запись удалится по его завершении
+    pub async fn remove(&self, game_id: &str) -> Result<Vec<QueueEntry>> {
+        match self.status(game_id).await? {
+            Some(QueueStatus::Running) => self.games.cancel_update(game_id)?,
+            Some(QueueStatus::Paused) => {
+                // После перезапуска приложения приостановленной загрузки в
+                // GameManager нет, остаётся только запись очереди
+                if self.games.is_paused(game_id) {
+                    self.games.cancel_update(game_id)?;
+                }
+                db::remove(&self.pool, game_id).await?;
+            }
+            Some(QueueStatus::Queued) => {
+                db::remove(&self.pool, game_id).await?;
+            }
+            None => {}
+        }
+        self.changed().await
+    }
+
+    // Отмена обновления из очереди или запущенного напрямую
+    pub async fn cancel(&self, game_id: &str) -> Result<()> {
+        match self.status(game_id).await? {
+            Some(_) => self.remove(game_id).await.map(|_| ()),
+            None => self.games.cancel_update(game_id),
+        }
+    }
+
+    // Приостановленное обновление снова ставится в очередь и запускается
+    // диспетчером с учётом лимитов; steamcmd продолжит загрузку
+    pub async fn resume(&self, game_id: &str) -> Result<Vec<QueueEntry>> {
+        match self.status(game_id).await? {
+            Some(QueueStatus::Paused) => {
+                db::set_status(&self.pool, game_id, QueueStatus::Queued).await?;
+            }
+            // Обновление, запущенное не из очереди
+            None if self.games.is_paused(game_id) => {
+                let game = self.games.find_game(game_id).await?;
+                db::enqueue(&self.pool, game_id, game.platform, 0, QueueOrigin::Manual).await?;
+            }
+            _ => {
+                return Err(Error::UpdateFailed(format!("Update of {} is not paused", game_id)));
+            }
+        }
+        log_info!("Update of {} resumed", game_id);
+        self.changed().await
+    }
+
+    async fn status(&self, game_id: &str) -> Result<Option<QueueStatus>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|entry| entry.game_id == game_id)
+            .map(|entry| entry.status))
+    }
+
+    async fn dispatch(&self) -> Result<()> {
+        let entries = self.list().await?;
+
+        let mut running: HashMap<_, usize> = HashMap::new();
+        for entry in entries.iter().filter(|e| e.status == QueueStatus::Running) {
+            *running.entry(entry.platform).or_default() += 1;
+        }
+
+        let mut started = false;
+        for entry in entries.iter().filter(|e| e.status == QueueStatus::Queued) {
+            let count = running.entry(entry.platform).or_default();
+            if *count >= self.limits.for_platform(entry.platform) {
+                continue;
+            }
+
+            *count += 1;
+            db::set_status(&self.pool, &entry.game_id, QueueStatus::Running).await?;
+            self.spawn_update(entry.game_id.clone());
+            started = true;
+        }
+
+        if started {
+            self.changed().await?;
+        }
+        Ok(())
+    }
+
+    fn spawn_update(&self, game_id: String) {
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            log_info!("Starting queued update of {}", game_id);
+            if let Err(e) = manager.games.update_game(&game_id).await {
+                log_warn!("Queued update of {} failed: {}", game_id, e);
+            }
+
+            // Приостановленная запись остаётся в очереди до resume или удаления
+            let result = match manager.games.is_paused(&game_id) {
+                true => db::set_status(&manager.pool, &game_id, QueueStatus::Paused).await,
+                false => db::remove(&manager.pool, &game_id).await.map(|_| ()),
+            };
+            if let Err(e) = result {
+                log_error!("Failed to update {} in update queue: {}", game_id, e);
+            }
+            if let Err(e) = manager.changed().await {
+                log_error!("Failed to emit queue-changed: {}", e);
+            }
+        });
+    }
+
+    // Оповещает фронтенд и будит диспетчер
+    async fn changed(&self) -> Result<Vec<QueueEntry>> {
+        self.wake.notify_one();
+        let entries = self.list().await?;
+        self.app.emit("queue-changed", &entries)?;
+        Ok(entries)
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::games::Platform;
+use crate::settings::{MAX_CONCURRENT_EPIC_UPDATES, MAX_CONCURRENT_STEAM_UPDATES};
+
+mod db;
+pub mod manager;
+
+pub use manager::QueueManager;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    Queued,
+    Running,
+    // Обновление приостановлено; запись ждёт resume_update
+    Paused,
+}
+
+// Кто поставил обновление в очередь
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOrigin {
+    Manual,
+    // Планировщик; такие записи зависят от окна обслуживания
+    Auto,
+}
+
+// Запись очереди. Порядок запуска - position по возрастанию; priority
+// определяет место при постановке: перед записями с меньшим приоритетом
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEntry {
+    pub id: i64,
+    pub game_id: String,
+    pub platform: Platform,
+    pub priority: i64,
+    pub position: i64,
+    pub status: QueueStatus,
+    pub origin: QueueOrigin,
+    pub enqueued_at: Option<DateTime<Utc>>,
+}
+
+// Сколько обновлений одной платформы выполняется одновременно
+#[derive(Debug, Clone, Copy)]
+pub struct QueueLimits {
+    pub steam: usize,
+    pub epic: usize,
+}
+
+impl QueueLimits {
+    pub fn for_platform(&self, platform: Platform) -> usize {
+        match platform {
+            Platform::Steam => self.steam,
+            Platform::Epic => self.epic,
+        }
+    }
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        Self {
+            steam: MAX_CONCURRENT_STEAM_UPDATES,
+            epic: MAX_CONCURRENT_EPIC_UPDATES,
+        }
+    }
+}
@@ -1,22 +1,26 @@
-use super::{RegistryReader, WindowsRegistry};
+use super::{DefaultRegistry, RegistryBackend, RegistryReader};
 use crate::error::{Result, Error};
 use std::path::PathBuf;
 
-pub struct EpicRegistry {
-    registry: WindowsRegistry,
+pub struct EpicRegistry<B: RegistryBackend = DefaultRegistry> {
+    registry: B,
 }
 
 impl EpicRegistry {
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            registry: WindowsRegistry::new(),
-        })
+        Ok(Self::with_backend(DefaultRegistry::new()))
     }
 }
 
-impl RegistryReader for EpicRegistry {
+impl<B: RegistryBackend> EpicRegistry<B> {
+    pub fn with_backend(registry: B) -> Self {
+        Self { registry }
+    }
+}
+
+impl<B: RegistryBackend> RegistryReader for EpicRegistry<B> {
     fn get_install_path(&self) -> Result<PathBuf> {
-        let path = self.registry.get_value_from_path(
+        let path = self.registry.get_value(
             "SOFTWARE\\Epic Games\\EpicGamesLauncher",
             "AppDataPath"
         )?;
@@ -68,3 +72,60 @@ impl RegistryReader for EpicRegistry {
         Ok(games)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::MemoryRegistry;
+
+    const EPIC_JSON: &str = include_str!("../../tests/fixtures/registry/epic.json");
+
+    #[test]
+    fn games_are_read_from_launcher_manifests() {
+        let data = std::env::temp_dir().join(format!("updateio-epic-{}", std::process::id()));
+        let manifests = data.join("Manifests");
+        std::fs::create_dir_all(&manifests).unwrap();
+        std::fs::write(
+            manifests.join("A1B2C3.item"),
+            r#"{"FormatVersion": 0, "DisplayName": "Fortnite", "CatalogItemId": "4fe75bbc5a674f4f9b356b5c90567da5", "InstallLocation": "C:\\Program Files\\Epic Games\\Fortnite"}"#,
+        )
+        .unwrap();
+        // Незавершённые установки и посторонние файлы пропускаются
+        std::fs::write(manifests.join("D4E5F6.item"), r#"{"DisplayName": "Broken"}"#).unwrap();
+        std::fs::write(manifests.join("notes.txt"), "{}").unwrap();
+
+        let fixture = EPIC_JSON.replace(
+            r"C:\\ProgramData\\Epic\\EpicGamesLauncher\\Data\\",
+            &data.to_string_lossy(),
+        );
+        let registry = EpicRegistry::with_backend(MemoryRegistry::from_json_str(&fixture).unwrap());
+
+        let install_path = registry.get_install_path();
+        let folders = registry.get_library_folders();
+        let games = registry.get_installed_games();
+        std::fs::remove_dir_all(&data).unwrap();
+
+        assert_eq!(install_path.unwrap(), data);
+        assert_eq!(folders.unwrap(), vec![manifests]);
+        assert_eq!(
+            games.unwrap(),
+            vec![(
+                "4fe75bbc5a674f4f9b356b5c90567da5".to_string(),
+                PathBuf::from("C:\\Program Files\\Epic Games\\Fortnite"),
+            )]
+        );
+    }
+
+    #[test]
+    fn launcher_without_manifests_has_no_games() {
+        let registry = EpicRegistry::with_backend(MemoryRegistry::new().with_value(
+            "SOFTWARE\\Epic Games\\EpicGamesLauncher",
+            "AppDataPath",
+            "/nonexistent/updateio-epic",
+        ));
+        assert!(registry.get_library_folders().unwrap().is_empty());
+        assert!(registry.get_installed_games().unwrap().is_empty());
+
+        assert!(EpicRegistry::with_backend(MemoryRegistry::new()).get_install_path().is_err());
+    }
+}
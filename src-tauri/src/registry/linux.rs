@@ -0,0 +1,139 @@
+use directories::BaseDirs;
+use std::path::{Path, PathBuf};
+
+// Поиск установки Steam в домашнем каталоге (Linux, Flatpak)
+#[derive(Debug, Clone)]
+pub struct LinuxSteamLocator {
+    home: PathBuf,
+}
+
+impl LinuxSteamLocator {
+    pub fn new() -> Option<Self> {
+        BaseDirs::new().map(|dirs| Self::with_home(dirs.home_dir()))
+    }
+
+    pub fn with_home(home: impl Into<PathBuf>) -> Self {
+        Self { home: home.into() }
+    }
+
+    // Порядок важен: ~/.steam/* обычно являются ссылками на реальный каталог
+    pub fn candidates(&self) -> Vec<PathBuf> {
+        let flatpak = self.home.join(".var/app/com.valvesoftware.Steam");
+        vec![
+            self.home.join(".steam/steam"),
+            self.home.join(".steam/root"),
+            self.home.join(".local/share/Steam"),
+            flatpak.join(".local/share/Steam"),
+            flatpak.join("data/Steam"),
+        ]
+    }
+
+    // Существующие установки без повторов после разрешения ссылок
+    pub fn find_installations(&self) -> Vec<PathBuf> {
+        let mut installations = Vec::new();
+        for candidate in self.candidates() {
+            if !is_steam_root(&candidate) {
+                continue;
+            }
+            let resolved = std::fs::canonicalize(&candidate).unwrap_or(candidate);
+            if !installations.contains(&resolved) {
+                installations.push(resolved);
+            }
+        }
+        installations
+    }
+}
+
+fn is_steam_root(path: &Path) -> bool {
+    path.join("steamapps").is_dir()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::registry::steam::SteamRegistry;
+    use crate::registry::{MemoryRegistry, RegistryReader};
+    use std::os::unix::fs::symlink;
+
+    // Домашний каталог с нативным Steam, ссылками ~/.steam/* на него и Flatpak
+    fn home(name: &str) -> PathBuf {
+        let home = std::env::temp_dir().join(format!("updateio-linux-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+
+        let native = home.join(".local/share/Steam");
+        std::fs::create_dir_all(native.join("steamapps")).unwrap();
+        std::fs::create_dir_all(home.join(".steam")).unwrap();
+        symlink(&native, home.join(".steam/steam")).unwrap();
+        symlink(&native, home.join(".steam/root")).unwrap();
+
+        let flatpak = home.join(".var/app/com.valvesoftware.Steam");
+        std::fs::create_dir_all(flatpak.join(".local/share/Steam/steamapps")).unwrap();
+        std::fs::create_dir_all(flatpak.join("data")).unwrap();
+        symlink(flatpak.join(".local/share/Steam"), flatpak.join("data/Steam")).unwrap();
+
+        home.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn symlinked_installations_are_found_once() {
+        let home = home("installations");
+        let locator = LinuxSteamLocator::with_home(&home);
+
+        assert_eq!(
+            locator.find_installations(),
+            vec![
+                home.join(".local/share/Steam"),
+                home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+            ]
+        );
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn missing_installations_are_skipped() {
+        let home = std::env::temp_dir().join(format!("updateio-linux-empty-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(home.join(".steam/steam")).unwrap();
+
+        // Каталог без steamapps установкой не считается
+        assert!(LinuxSteamLocator::with_home(&home).find_installations().is_empty());
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    // Библиотеки указаны через ссылку и повторяются в обоих установках
+    #[test]
+    fn each_library_is_listed_once() {
+        let home = home("libraries");
+        let games = home.join("Games/SteamLibrary");
+        std::fs::create_dir_all(games.join("steamapps")).unwrap();
+
+        let folders = format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+            home.join(".steam/steam").display(),
+            games.display(),
+        );
+        std::fs::write(home.join(".local/share/Steam/steamapps/libraryfolders.vdf"), &folders).unwrap();
+        std::fs::write(
+            home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/libraryfolders.vdf"),
+            &folders,
+        )
+        .unwrap();
+
+        let registry = SteamRegistry::with_backend(MemoryRegistry::new())
+            .with_locator(LinuxSteamLocator::with_home(&home));
+
+        assert_eq!(
+            registry.get_library_folders().unwrap(),
+            vec![
+                home.join(".local/share/Steam/steamapps"),
+                games.join("steamapps"),
+                home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps"),
+            ]
+        );
+        assert_eq!(registry.get_install_path().unwrap(), home.join(".local/share/Steam"));
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
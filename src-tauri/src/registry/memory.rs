@@ -0,0 +1,275 @@
+use super::RegistryBackend;
+use crate::error::{Result, Error};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Реестр в памяти: фикстуры для тестов и платформы без реестра Windows.
+// Имена разделов и значений, как и в Windows, регистронезависимы.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRegistry {
+    keys: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl MemoryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_value(&mut self, path: &str, name: &str, value: impl Into<String>) {
+        self.keys
+            .entry(normalize_path(path))
+            .or_default()
+            .insert(name.to_lowercase(), value.into());
+    }
+
+    pub fn with_value(mut self, path: &str, name: &str, value: impl Into<String>) -> Self {
+        self.set_value(path, name, value);
+        self
+    }
+
+    // { "SOFTWARE\\Valve\\Steam": { "InstallPath": "C:\\Steam" } }
+    pub fn from_json_str(content: &str) -> Result<Self> {
+        let root: serde_json::Map<String, serde_json::Value> = serde_json::from_str(content)
+            .map_err(|e| Error::ParseError(format!("Invalid registry fixture: {}", e)))?;
+
+        let mut registry = Self::new();
+        for (path, values) in root {
+            let values = values.as_object().ok_or_else(|| {
+                Error::ParseError(format!("Registry key {} must be an object", path))
+            })?;
+            for (name, value) in values {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                registry.set_value(&path, name, value);
+            }
+        }
+        Ok(registry)
+    }
+
+    // Экспорт regedit (REGEDIT4 или "Windows Registry Editor Version 5.00")
+    pub fn from_reg_str(content: &str) -> Result<Self> {
+        let mut registry = Self::new();
+        let mut current: Option<String> = None;
+        let mut lines = content.trim_start_matches('\u{feff}').lines();
+
+        while let Some(line) = lines.next() {
+            let mut line = line.trim().to_string();
+            // Длинные hex-значения переносятся на следующую строку через '\'
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some(next) => line.push_str(next.trim()),
+                    None => break,
+                }
+            }
+
+            if line.is_empty() || line.starts_with(';') || line.starts_with("Windows Registry") || line == "REGEDIT4" {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                // Удаление раздела ([-HKEY_...]) не поддерживаем
+                current = if section.starts_with('-') {
+                    None
+                } else {
+                    Some(section.to_string())
+                };
+                continue;
+            }
+
+            let path = match &current {
+                Some(path) => path,
+                None => continue,
+            };
+
+            if let Some((name, value)) = parse_reg_value(&line) {
+                registry.set_value(path, &name, value);
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| Error::FileError(format!("Failed to read {}: {}", path.display(), e)))?;
+
+        // regedit сохраняет файлы в UTF-16LE с BOM
+        let content = if bytes.starts_with(&[0xFF, 0xFE]) {
+            let units: Vec<u16> = bytes[2..]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::from_json_str(&content),
+            _ => Self::from_reg_str(&content),
+        }
+    }
+}
+
+impl RegistryBackend for MemoryRegistry {
+    fn get_value(&self, path: &str, name: &str) -> Result<String> {
+        let key = self
+            .keys
+            .get(&normalize_path(path))
+            .ok_or_else(|| Error::Registry(format!("Registry key not found: {}", path)))?;
+
+        key.get(&name.to_lowercase())
+            .cloned()
+            .ok_or_else(|| Error::Registry(format!("Registry value not found: {}\\{}", path, name)))
+    }
+}
+
+// Пути считаются относительно HKEY_LOCAL_MACHINE, префикс раздела необязателен
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('/', "\\");
+    let path = path.trim_matches('\\');
+    let lower = path.to_lowercase();
+
+    let stripped = ["hkey_local_machine\\", "hklm\\"]
+        .iter()
+        .find_map(|prefix| lower.strip_prefix(prefix))
+        .unwrap_or(&lower);
+
+    stripped.to_string()
+}
+
+fn parse_reg_value(line: &str) -> Option<(String, String)> {
+    let (name, rest) = if let Some(rest) = line.strip_prefix('@') {
+        (String::new(), rest)
+    } else {
+        let (name, len) = read_reg_string(line.strip_prefix('"')?)?;
+        (name, &line[len + 1..])
+    };
+
+    let data = rest.trim_start().strip_prefix('=')?.trim();
+
+    if let Some(quoted) = data.strip_prefix('"') {
+        return read_reg_string(quoted).map(|(value, _)| (name, value));
+    }
+
+    if let Some(hex) = data.strip_prefix("dword:") {
+        return u32::from_str_radix(hex.trim(), 16)
+            .ok()
+            .map(|value| (name, value.to_string()));
+    }
+
+    // hex(...) и прочие двоичные типы игнорируем
+    None
+}
+
+// Читает строку до закрывающей кавычки, возвращает значение и длину в байтах
+fn read_reg_string(input: &str) -> Option<(String, usize)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, i + 1)),
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            c => value.push(c),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEAM_REG: &str = include_str!("../../tests/fixtures/registry/steam.reg");
+    const EPIC_JSON: &str = include_str!("../../tests/fixtures/registry/epic.json");
+
+    #[test]
+    fn reg_export_is_parsed() {
+        let registry = MemoryRegistry::from_reg_str(STEAM_REG).unwrap();
+
+        assert_eq!(
+            registry.get_value("SOFTWARE\\WOW6432Node\\Valve\\Steam", "InstallPath").unwrap(),
+            "C:\\Program Files (x86)\\Steam"
+        );
+        // dword хранится десятичным числом
+        assert_eq!(registry.get_value("SOFTWARE\\WOW6432Node\\Valve\\Steam", "SteamPID").unwrap(), "6699");
+        assert_eq!(registry.get_value("SOFTWARE\\WOW6432Node\\Valve\\Steam\\Apps\\570", "Name").unwrap(), "Dota 2");
+        // Двоичное значение с переносом строки пропускается и не ломает разбор следующих
+        assert!(registry.get_value("SOFTWARE\\WOW6432Node\\Valve\\Steam", "Language").is_err());
+    }
+
+    #[test]
+    fn lookup_ignores_case_and_hive_prefix() {
+        let registry = MemoryRegistry::from_reg_str(STEAM_REG).unwrap();
+
+        for path in [
+            "software\\wow6432node\\valve\\steam",
+            "HKLM\\SOFTWARE\\WOW6432Node\\Valve\\Steam",
+            "HKEY_LOCAL_MACHINE/SOFTWARE/WOW6432Node/Valve/Steam/",
+        ] {
+            assert_eq!(registry.get_value(path, "installpath").unwrap(), "C:\\Program Files (x86)\\Steam");
+        }
+        assert!(registry.get_value("SOFTWARE\\Valve\\Steam", "InstallPath").is_err());
+    }
+
+    #[test]
+    fn deleted_keys_and_default_values() {
+        let content = "REGEDIT4\n\n[HKEY_LOCAL_MACHINE\\SOFTWARE\\Test]\n@=\"default\"\n\"Quoted\"=\"say \\\"hi\\\"\"\n\n[-HKEY_LOCAL_MACHINE\\SOFTWARE\\Removed]\n\"Value\"=\"ignored\"\n";
+        let registry = MemoryRegistry::from_reg_str(content).unwrap();
+
+        assert_eq!(registry.get_value("SOFTWARE\\Test", "").unwrap(), "default");
+        assert_eq!(registry.get_value("SOFTWARE\\Test", "Quoted").unwrap(), "say \"hi\"");
+        assert!(registry.get_value("SOFTWARE\\Removed", "Value").is_err());
+    }
+
+    #[test]
+    fn json_fixture_is_parsed() {
+        let registry = MemoryRegistry::from_json_str(EPIC_JSON).unwrap();
+
+        assert_eq!(
+            registry.get_value("SOFTWARE\\Epic Games\\EpicGamesLauncher", "AppDataPath").unwrap(),
+            "C:\\ProgramData\\Epic\\EpicGamesLauncher\\Data\\"
+        );
+        assert_eq!(registry.get_value("SOFTWARE\\Valve\\Steam", "InstallPath").unwrap(), "C:\\Steam");
+        assert_eq!(registry.get_value("SOFTWARE\\Valve\\Steam", "Version").unwrap(), "2");
+
+        assert!(MemoryRegistry::from_json_str("{\"SOFTWARE\\\\Test\": \"value\"}").is_err());
+        assert!(MemoryRegistry::from_json_str("[]").is_err());
+    }
+
+    #[test]
+    fn files_are_read_by_extension_and_encoding() {
+        let dir = std::env::temp_dir().join(format!("updateio-registry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // regedit сохраняет .reg в UTF-16LE с BOM
+        let reg = dir.join("steam.reg");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(STEAM_REG.encode_utf16().flat_map(u16::to_le_bytes));
+        std::fs::write(&reg, bytes).unwrap();
+
+        let json = dir.join("epic.JSON");
+        std::fs::write(&json, EPIC_JSON).unwrap();
+
+        let from_reg = MemoryRegistry::from_file(&reg);
+        let from_json = MemoryRegistry::from_file(&json);
+        let missing = MemoryRegistry::from_file(&dir.join("missing.reg"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            from_reg.unwrap().get_value("SOFTWARE\\WOW6432Node\\Valve\\Steam\\Apps\\570", "Installed").unwrap(),
+            "1"
+        );
+        assert_eq!(from_json.unwrap().get_value("SOFTWARE\\Valve\\Steam", "InstallPath").unwrap(), "C:\\Steam");
+        assert!(missing.is_err());
+    }
+}
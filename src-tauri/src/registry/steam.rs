@@ -1,102 +1,824 @@
-use super::{RegistryReader, WindowsRegistry};
+use super::{DefaultRegistry, LinuxSteamLocator, RegistryBackend, RegistryReader};
 use crate::error::{Result, Error};
-use std::path::PathBuf;
+use crate::vdf::{self, AppInfo, AppInfoIndex};
+use crate::log_warn;
+use parking_lot::Mutex;
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
-pub struct SteamRegistry {
-    registry: WindowsRegistry,
+// steamapps/appmanifest_<appid>.acf
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppManifest {
+    #[serde(rename = "appid")]
+    pub app_id: u32,
+    pub universe: u32,
+    pub name: String,
+    #[serde(rename = "StateFlags")]
+    pub state_flags: u32,
+    #[serde(rename = "installdir")]
+    pub install_dir: String,
+    #[serde(rename = "LastUpdated")]
+    pub last_updated: i64,
+    #[serde(rename = "SizeOnDisk")]
+    pub size_on_disk: u64,
+    #[serde(rename = "StagingSize")]
+    pub staging_size: u64,
+    #[serde(rename = "buildid")]
+    pub build_id: u32,
+    #[serde(rename = "LastOwner")]
+    pub last_owner: u64,
+    #[serde(rename = "UpdateResult")]
+    pub update_result: u32,
+    #[serde(rename = "BytesToDownload")]
+    pub bytes_to_download: u64,
+    #[serde(rename = "BytesDownloaded")]
+    pub bytes_downloaded: u64,
+    #[serde(rename = "BytesToStage")]
+    pub bytes_to_stage: u64,
+    #[serde(rename = "BytesStaged")]
+    pub bytes_staged: u64,
+    #[serde(rename = "TargetBuildID")]
+    pub target_build_id: u32,
+    #[serde(rename = "AutoUpdateBehavior")]
+    pub auto_update_behavior: u32,
+    #[serde(rename = "InstalledDepots")]
+    pub installed_depots: BTreeMap<u32, InstalledDepot>,
+    #[serde(rename = "UserConfig")]
+    pub user_config: AppConfig,
+    #[serde(rename = "MountedConfig")]
+    pub mounted_config: AppConfig,
 }
 
-impl SteamRegistry {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            registry: WindowsRegistry::new(),
-        })
+// Биты StateFlags (EAppState)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AppStateFlags(pub u32);
+
+impl AppStateFlags {
+    pub const UNINSTALLED: Self = Self(0x1);
+    pub const UPDATE_REQUIRED: Self = Self(0x2);
+    pub const FULLY_INSTALLED: Self = Self(0x4);
+    pub const ENCRYPTED: Self = Self(0x8);
+    pub const LOCKED: Self = Self(0x10);
+    pub const FILES_MISSING: Self = Self(0x20);
+    pub const APP_RUNNING: Self = Self(0x40);
+    pub const FILES_CORRUPT: Self = Self(0x80);
+    pub const UPDATE_RUNNING: Self = Self(0x100);
+    pub const UPDATE_PAUSED: Self = Self(0x200);
+    pub const UPDATE_STARTED: Self = Self(0x400);
+    pub const UNINSTALLING: Self = Self(0x800);
+    pub const BACKUP_RUNNING: Self = Self(0x1000);
+    pub const RECONFIGURING: Self = Self(0x10000);
+    pub const VALIDATING: Self = Self(0x20000);
+    pub const ADDING_FILES: Self = Self(0x40000);
+    pub const PREALLOCATING: Self = Self(0x80000);
+    pub const DOWNLOADING: Self = Self(0x100000);
+    pub const STAGING: Self = Self(0x200000);
+    pub const COMMITTING: Self = Self(0x400000);
+    pub const UPDATE_STOPPING: Self = Self(0x800000);
+
+    const NAMES: &'static [(Self, &'static str)] = &[
+        (Self::UNINSTALLED, "Uninstalled"),
+        (Self::UPDATE_REQUIRED, "UpdateRequired"),
+        (Self::FULLY_INSTALLED, "FullyInstalled"),
+        (Self::ENCRYPTED, "Encrypted"),
+        (Self::LOCKED, "Locked"),
+        (Self::FILES_MISSING, "FilesMissing"),
+        (Self::APP_RUNNING, "AppRunning"),
+        (Self::FILES_CORRUPT, "FilesCorrupt"),
+        (Self::UPDATE_RUNNING, "UpdateRunning"),
+        (Self::UPDATE_PAUSED, "UpdatePaused"),
+        (Self::UPDATE_STARTED, "UpdateStarted"),
+        (Self::UNINSTALLING, "Uninstalling"),
+        (Self::BACKUP_RUNNING, "BackupRunning"),
+        (Self::RECONFIGURING, "Reconfiguring"),
+        (Self::VALIDATING, "Validating"),
+        (Self::ADDING_FILES, "AddingFiles"),
+        (Self::PREALLOCATING, "Preallocating"),
+        (Self::DOWNLOADING, "Downloading"),
+        (Self::STAGING, "Staging"),
+        (Self::COMMITTING, "Committing"),
+        (Self::UPDATE_STOPPING, "UpdateStopping"),
+    ];
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
     }
 
-    fn parse_vdf_file(&self, path: &PathBuf) -> Result<Vec<PathBuf>> {
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
-        
-        let mut folders = Vec::new();
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with("\"path\"") {
-                if let Some(path) = line.split('"').nth(3) {
-                    folders.push(PathBuf::from(path).join("steamapps"));
-                }
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl std::ops::BitOr for AppStateFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl Serialize for AppStateFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.names().serialize(serializer)
+    }
+}
+
+// Состояние установки, восстановленное из манифеста без запуска steamcmd
+#[derive(Debug, Clone, Serialize)]
+pub struct SteamInstallState {
+    pub flags: AppStateFlags,
+    pub update_result: u32,
+    pub bytes_to_download: u64,
+    pub bytes_downloaded: u64,
+    pub build_id: u32,
+    pub target_build_id: u32,
+    // Сборка из appcache/appinfo.vdf, если клиент Steam её знает
+    pub remote_build_id: Option<u32>,
+    pub needs_update: bool,
+    pub is_updating: bool,
+}
+
+impl SteamInstallState {
+    pub fn from_manifest(manifest: &AppManifest) -> Self {
+        let flags = AppStateFlags(manifest.state_flags);
+
+        let is_updating = flags.intersects(
+            AppStateFlags::UPDATE_RUNNING
+                | AppStateFlags::DOWNLOADING
+                | AppStateFlags::STAGING
+                | AppStateFlags::COMMITTING
+                | AppStateFlags::VALIDATING
+                | AppStateFlags::PREALLOCATING,
+        );
+
+        let needs_update = flags.intersects(
+            AppStateFlags::UPDATE_REQUIRED
+                | AppStateFlags::UPDATE_PAUSED
+                | AppStateFlags::UPDATE_STARTED
+                | AppStateFlags::FILES_MISSING
+                | AppStateFlags::FILES_CORRUPT,
+        ) || manifest.bytes_to_download > manifest.bytes_downloaded
+            || (manifest.target_build_id != 0 && manifest.target_build_id != manifest.build_id);
+
+        Self {
+            flags,
+            update_result: manifest.update_result,
+            bytes_to_download: manifest.bytes_to_download,
+            bytes_downloaded: manifest.bytes_downloaded,
+            build_id: manifest.build_id,
+            target_build_id: manifest.target_build_id,
+            remote_build_id: None,
+            needs_update,
+            is_updating,
+        }
+    }
+}
+
+impl SteamInstallState {
+    pub fn with_remote_build(mut self, remote_build_id: Option<u32>) -> Self {
+        self.remote_build_id = remote_build_id;
+        if remote_build_id.is_some_and(|remote| remote != self.build_id) {
+            self.needs_update = true;
+        }
+        self
+    }
+}
+
+impl AppManifest {
+    // Ветка, на которую подписан пользователь
+    pub fn branch(&self) -> &str {
+        self.user_config
+            .beta_key
+            .as_deref()
+            .or(self.mounted_config.beta_key.as_deref())
+            .filter(|branch| !branch.is_empty())
+            .unwrap_or("public")
+    }
+
+    pub fn install_state(&self) -> SteamInstallState {
+        SteamInstallState::from_manifest(self)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct InstalledDepot {
+    pub manifest: String,
+    pub size: u64,
+    #[serde(rename = "dlcappid")]
+    pub dlc_app_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub language: Option<String>,
+    #[serde(rename = "BetaKey")]
+    pub beta_key: Option<String>,
+}
+
+// steamapps/libraryfolders.vdf
+#[derive(Debug, Clone, Default)]
+pub struct LibraryFolders {
+    pub folders: Vec<LibraryFolder>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LibraryFolder {
+    pub path: PathBuf,
+    pub label: String,
+    #[serde(rename = "totalsize")]
+    pub total_size: u64,
+    pub apps: BTreeMap<u32, u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstalledApp {
+    pub manifest: AppManifest,
+    pub library: PathBuf,
+    pub install_path: PathBuf,
+}
+
+// userdata/<account>/config/shortcuts.vdf
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Shortcut {
+    // Для ярлыков Steam хранит appid как int32 с установленным старшим битом
+    #[serde(rename = "appid")]
+    pub app_id: i32,
+    #[serde(rename = "AppName")]
+    pub app_name: String,
+    #[serde(rename = "Exe")]
+    pub exe: String,
+    #[serde(rename = "StartDir")]
+    pub start_dir: String,
+    pub icon: String,
+    #[serde(rename = "LaunchOptions")]
+    pub launch_options: String,
+    #[serde(rename = "IsHidden")]
+    pub is_hidden: bool,
+    #[serde(rename = "LastPlayTime")]
+    pub last_play_time: i64,
+    pub tags: Vec<String>,
+}
+
+// Старый формат: "1" "D:\\SteamLibrary", новый: "0" { "path" ... }
+struct LibraryEntry(LibraryFolder);
+
+impl<'de> Deserialize<'de> for LibraryEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = LibraryEntry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("library path or library folder section")
+            }
+
+            fn visit_str<E: de::Error>(self, path: &str) -> std::result::Result<Self::Value, E> {
+                Ok(LibraryEntry(LibraryFolder {
+                    path: PathBuf::from(path),
+                    ..Default::default()
+                }))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<Self::Value, A::Error> {
+                LibraryFolder::deserialize(de::value::MapAccessDeserializer::new(map)).map(LibraryEntry)
             }
         }
-        Ok(folders)
+
+        deserializer.deserialize_any(EntryVisitor)
     }
+}
 
-    fn parse_acf_file(&self, path: &PathBuf) -> Result<Option<(String, String)>> {
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
+impl<'de> Deserialize<'de> for LibraryFolders {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct FoldersVisitor;
 
-        let mut name = None;
-        let mut install_dir = None;
+        impl<'de> Visitor<'de> for FoldersVisitor {
+            type Value = LibraryFolders;
 
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with("\"name\"") {
-                if let Some(n) = line.split('"').nth(3) {
-                    name = Some(n.to_string());
-                }
-            } else if line.starts_with("\"installdir\"") {
-                if let Some(dir) = line.split('"').nth(3) {
-                    install_dir = Some(dir.to_string());
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("libraryfolders section")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+                let mut folders = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    // Служебные ключи вроде "contentstatsid" пропускаем
+                    if key.parse::<u32>().is_ok() {
+                        folders.push(map.next_value::<LibraryEntry>()?.0);
+                    } else {
+                        map.next_value::<IgnoredAny>()?;
+                    }
                 }
+                Ok(LibraryFolders { folders })
             }
         }
 
-        match (name, install_dir) {
-            (Some(name), Some(dir)) => Ok(Some((name, dir))),
-            _ => Ok(None),
+        deserializer.deserialize_map(FoldersVisitor)
+    }
+}
+
+pub struct SteamRegistry<B: RegistryBackend = DefaultRegistry> {
+    registry: B,
+    locator: Option<LinuxSteamLocator>,
+}
+
+impl SteamRegistry {
+    pub fn new() -> Result<Self> {
+        let registry = Self::with_backend(DefaultRegistry::new());
+
+        // Вне Windows Steam ищем в домашнем каталоге
+        if cfg!(windows) {
+            Ok(registry)
+        } else {
+            Ok(match LinuxSteamLocator::new() {
+                Some(locator) => registry.with_locator(locator),
+                None => registry,
+            })
         }
     }
 }
 
-impl RegistryReader for SteamRegistry {
-    fn get_install_path(&self) -> Result<PathBuf> {
-        self.registry.get_install_path()
+impl<B: RegistryBackend> SteamRegistry<B> {
+    pub fn with_backend(registry: B) -> Self {
+        Self {
+            registry,
+            locator: None,
+        }
     }
 
-    fn get_library_folders(&self) -> Result<Vec<PathBuf>> {
-        let steam_path = self.get_install_path()?;
-        let mut folders = vec![steam_path.join("steamapps")];
-        
-        let vdf_path = steam_path.join("steamapps/libraryfolders.vdf");
-        if vdf_path.exists() {
-            folders.extend(self.parse_vdf_file(&vdf_path)?);
+    pub fn with_locator(mut self, locator: LinuxSteamLocator) -> Self {
+        self.locator = Some(locator);
+        self
+    }
+
+    fn get_registry_install_path(&self) -> Result<PathBuf> {
+        // Сначала пробуем 64-битный раздел
+        let result = self.registry.get_value("SOFTWARE\\Valve\\Steam", "InstallPath");
+
+        if let Ok(path) = result {
+            return Ok(PathBuf::from(path));
         }
 
-        Ok(folders)
+        // Если не нашли, пробуем 32-битный раздел
+        let result = self.registry.get_value("SOFTWARE\\WOW6432Node\\Valve\\Steam", "InstallPath");
+
+        match result {
+            Ok(path) => Ok(PathBuf::from(path)),
+            Err(e) => Err(Error::Registry(format!("Steam не найден ни в 32-битном, ни в 64-битном реестре: {}", e)))
+        }
     }
 
-    fn get_installed_games(&self) -> Result<Vec<(String, PathBuf)>> {
-        let mut games = Vec::new();
+    // Все найденные установки Steam; на Linux их может быть несколько (нативная и Flatpak)
+    fn get_install_paths(&self) -> Result<Vec<PathBuf>> {
+        let registry_error = match self.get_registry_install_path() {
+            Ok(path) => return Ok(vec![path]),
+            Err(e) => e,
+        };
+
+        let installations = self
+            .locator
+            .as_ref()
+            .map(LinuxSteamLocator::find_installations)
+            .unwrap_or_default();
+
+        if installations.is_empty() {
+            Err(registry_error)
+        } else {
+            Ok(installations)
+        }
+    }
+
+    fn parse_vdf_file(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::ProcessError(e.to_string()))?;
+
+        let library: LibraryFolders = vdf::from_str(&content)?;
+        Ok(library
+            .folders
+            .into_iter()
+            .map(|folder| folder.path.join("steamapps"))
+            .collect())
+    }
+
+    fn parse_acf_file(&self, path: &Path) -> Result<AppManifest> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::ProcessError(e.to_string()))?;
+
+        vdf::from_str(&content)
+    }
+
+    pub fn get_installed_apps(&self) -> Result<Vec<InstalledApp>> {
+        let mut apps = Vec::new();
         let libraries = self.get_library_folders()?;
 
         for library in libraries {
+            // Библиотека на отключённом диске
+            if !library.is_dir() {
+                continue;
+            }
+
             for entry in std::fs::read_dir(&library)
                 .map_err(|e| Error::ProcessError(e.to_string()))?
             {
                 let entry = entry.map_err(|e| Error::ProcessError(e.to_string()))?;
                 let path = entry.path();
-                
-                if let Some(ext) = path.extension() {
-                    if ext == "acf" {
-                        if let Ok(Some((name, install_dir))) = self.parse_acf_file(&path) {
-                            games.push((
-                                name,
-                                library.join("common").join(install_dir)
-                            ));
+
+                if path.extension().is_some_and(|ext| ext == "acf") {
+                    match self.parse_acf_file(&path) {
+                        Ok(manifest) if manifest.app_id != 0 && !manifest.install_dir.is_empty() => {
+                            let install_path = library.join("common").join(&manifest.install_dir);
+                            apps.push(InstalledApp {
+                                manifest: self.latest_manifest(&install_path, manifest),
+                                install_path,
+                                library: library.clone(),
+                            });
                         }
+                        Ok(_) => log_warn!("Incomplete Steam manifest: {}", path.display()),
+                        Err(e) => log_warn!("Failed to parse {}: {}", path.display(), e),
                     }
                 }
             }
         }
 
-        Ok(games)
+        Ok(apps)
+    }
+
+    pub fn find_app_manifest(&self, app_id: u32) -> Result<Option<AppManifest>> {
+        for library in self.get_library_folders()? {
+            let path = library.join(format!("appmanifest_{}.acf", app_id));
+            if path.exists() {
+                let manifest = self.parse_acf_file(&path)?;
+                let install_path = library.join("common").join(&manifest.install_dir);
+                return Ok(Some(self.latest_manifest(&install_path, manifest)));
+            }
+        }
+        Ok(None)
+    }
+
+    // steamcmd с force_install_dir пишет манифест в <папка игры>/steamapps,
+    // а манифест библиотеки обновляет только клиент Steam. Берётся более
+    // свежий из двух, иначе после обновления сборка выглядела бы старой
+    fn latest_manifest(&self, install_path: &Path, manifest: AppManifest) -> AppManifest {
+        let path = install_path
+            .join("steamapps")
+            .join(format!("appmanifest_{}.acf", manifest.app_id));
+        if !path.exists() {
+            return manifest;
+        }
+
+        match self.parse_acf_file(&path) {
+            Ok(updated) if updated.last_updated > manifest.last_updated => AppManifest {
+                install_dir: manifest.install_dir,
+                ..updated
+            },
+            Ok(_) => manifest,
+            Err(e) => {
+                log_warn!("Failed to parse {}: {}", path.display(), e);
+                manifest
+            }
+        }
+    }
+
+    // Данные из appcache/appinfo.vdf, который клиент Steam обновляет сам.
+    // Файл индексируется один раз, дальше читаются только нужные записи
+    pub fn get_app_info(&self, app_id: u32) -> Result<Option<AppInfo>> {
+        let path = self.get_install_path()?.join("appcache").join("appinfo.vdf");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let modified = modified_time(&path)?;
+        let index = appinfo_index(&path, modified)?;
+        let range = match index.entry_range(app_id) {
+            Some(range) => range,
+            None => return Ok(None),
+        };
+        let entry = read_range(&path, range)?;
+
+        // Клиент Steam переписал файл после построения индекса
+        if modified_time(&path)? != modified {
+            APPINFO_INDEX.lock().take();
+            let data = std::fs::read(&path)
+                .map_err(|e| Error::FileError(e.to_string()))?;
+            return vdf::find_appinfo(&data, app_id);
+        }
+
+        index.parse_entry(app_id, &entry).map(Some)
+    }
+
+    pub fn get_shortcuts(&self) -> Result<Vec<Shortcut>> {
+        let userdata = self.get_install_path()?.join("userdata");
+        let mut shortcuts = Vec::new();
+
+        if !userdata.exists() {
+            return Ok(shortcuts);
+        }
+
+        for entry in std::fs::read_dir(&userdata)
+            .map_err(|e| Error::ProcessError(e.to_string()))?
+        {
+            let entry = entry.map_err(|e| Error::ProcessError(e.to_string()))?;
+            let path = entry.path().join("config").join("shortcuts.vdf");
+            if !path.exists() {
+                continue;
+            }
+
+            let data = std::fs::read(&path)
+                .map_err(|e| Error::FileError(e.to_string()))?;
+            shortcuts.extend(vdf::from_binary::<Vec<Shortcut>>(&data)?);
+        }
+
+        Ok(shortcuts)
+    }
+}
+
+impl<B: RegistryBackend> RegistryReader for SteamRegistry<B> {
+    fn get_install_path(&self) -> Result<PathBuf> {
+        self.get_install_paths()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Registry("Steam installation not found".to_string()))
+    }
+
+    fn get_library_folders(&self) -> Result<Vec<PathBuf>> {
+        let mut folders = Vec::new();
+
+        for steam_path in self.get_install_paths()? {
+            push_library_folder(&mut folders, steam_path.join("steamapps"));
+
+            let vdf_path = steam_path.join("steamapps/libraryfolders.vdf");
+            if vdf_path.exists() {
+                // Новый формат включает и саму папку Steam
+                for folder in self.parse_vdf_file(&vdf_path)? {
+                    push_library_folder(&mut folders, folder);
+                }
+            }
+        }
+
+        Ok(folders)
+    }
+
+    fn get_installed_games(&self) -> Result<Vec<(String, PathBuf)>> {
+        Ok(self
+            .get_installed_apps()?
+            .into_iter()
+            .map(|app| (app.manifest.app_id.to_string(), app.install_path))
+            .collect())
+    }
+}
+
+struct CachedAppInfoIndex {
+    path: PathBuf,
+    modified: SystemTime,
+    index: Arc<AppInfoIndex>,
+}
+
+lazy_static::lazy_static! {
+    static ref APPINFO_INDEX: Mutex<Option<CachedAppInfoIndex>> = Mutex::new(None);
+}
+
+// Индекс перестраивается, когда меняется время изменения файла
+fn appinfo_index(path: &Path, modified: SystemTime) -> Result<Arc<AppInfoIndex>> {
+    let mut cached = APPINFO_INDEX.lock();
+    if let Some(cached) = cached.as_ref().filter(|c| c.path == path && c.modified == modified) {
+        return Ok(cached.index.clone());
+    }
+
+    let data = std::fs::read(path)
+        .map_err(|e| Error::FileError(e.to_string()))?;
+    let index = Arc::new(AppInfoIndex::build(&data)?);
+    *cached = Some(CachedAppInfoIndex {
+        path: path.to_path_buf(),
+        modified,
+        index: index.clone(),
+    });
+    Ok(index)
+}
+
+fn modified_time(path: &Path) -> Result<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| Error::FileError(format!("{}: {}", path.display(), e)))
+}
+
+fn read_range(path: &Path, range: Range<usize>) -> Result<Vec<u8>> {
+    let read = || -> std::io::Result<Vec<u8>> {
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(range.start as u64))?;
+        let mut buffer = vec![0; range.len()];
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    };
+    read().map_err(|e| Error::FileError(format!("{}: {}", path.display(), e)))
+}
+
+// Одна и та же библиотека может быть доступна по ссылкам (~/.steam/steam и т.п.).
+// В Windows canonicalize возвращает пути вида \\?\C:\..., поэтому там не используем.
+fn push_library_folder(folders: &mut Vec<PathBuf>, folder: PathBuf) {
+    let folder = if cfg!(windows) {
+        folder
+    } else {
+        std::fs::canonicalize(&folder).unwrap_or(folder)
+    };
+
+    if !folders.contains(&folder) {
+        folders.push(folder);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::MemoryRegistry;
+
+    const MANIFEST: &str = include_str!("../../tests/fixtures/steam/appmanifest_570.acf");
+    const STEAMCMD_MANIFEST: &str = include_str!("../../tests/fixtures/steam/appmanifest_570_steamcmd.acf");
+    const LIBRARY_FOLDERS: &str = include_str!("../../tests/fixtures/steam/libraryfolders.vdf");
+    const STEAM_REG: &str = include_str!("../../tests/fixtures/registry/steam.reg");
+    const APPINFO: &[u8] = include_bytes!("../../tests/fixtures/steam/appinfo_v28.vdf");
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("updateio-steam-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    // Установка Steam в dir, найденная через InstallPath в реестре
+    fn registry(dir: &Path) -> SteamRegistry<MemoryRegistry> {
+        SteamRegistry::with_backend(MemoryRegistry::new().with_value(
+            "SOFTWARE\\Valve\\Steam",
+            "InstallPath",
+            dir.to_string_lossy(),
+        ))
+    }
+
+    #[test]
+    fn steamcmd_manifest_in_game_folder_is_used_when_newer() {
+        let steam = temp_dir("steamcmd-manifest");
+        write(&steam.join("steamapps/appmanifest_570.acf"), MANIFEST);
+        let registry = registry(&steam);
+
+        assert_eq!(registry.find_app_manifest(570).unwrap().unwrap().build_id, 12500000);
+
+        // После app_update с force_install_dir на папку игры
+        let game = steam.join("steamapps/common/dota 2 beta");
+        write(&game.join("steamapps/appmanifest_570.acf"), STEAMCMD_MANIFEST);
+
+        let manifest = registry.find_app_manifest(570).unwrap().unwrap();
+        assert_eq!(manifest.build_id, 12600000);
+        assert_eq!(manifest.install_dir, "dota 2 beta");
+
+        let apps = registry.get_installed_apps().unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].manifest.build_id, 12600000);
+        assert_eq!(apps[0].install_path, std::fs::canonicalize(&game).unwrap());
+
+        // Клиент Steam обновил манифест библиотеки позже - снова берётся он
+        write(
+            &steam.join("steamapps/appmanifest_570.acf"),
+            &MANIFEST.replace("1700000000", "1700200000"),
+        );
+        assert_eq!(registry.find_app_manifest(570).unwrap().unwrap().build_id, 12500000);
+
+        std::fs::remove_dir_all(&steam).unwrap();
+    }
+
+    // Пути из фикстур заменяются временными каталогами
+    fn library(name: &str) -> (PathBuf, PathBuf, SteamRegistry<MemoryRegistry>) {
+        let root = temp_dir(name);
+        let steam = root.join("Steam");
+        let games = root.join("SteamLibrary");
+        std::fs::create_dir_all(games.join("steamapps")).unwrap();
+
+        write(
+            &steam.join("steamapps/libraryfolders.vdf"),
+            &LIBRARY_FOLDERS
+                .replace("C:\\\\Program Files (x86)\\\\Steam", &steam.to_string_lossy())
+                .replace("D:\\\\SteamLibrary", &games.to_string_lossy()),
+        );
+        // InstallPath только в 32-битном разделе
+        let reg = STEAM_REG.replace("C:\\\\Program Files (x86)\\\\Steam", &steam.to_string_lossy());
+        let registry = SteamRegistry::with_backend(MemoryRegistry::from_reg_str(&reg).unwrap());
+
+        (root.canonicalize().unwrap(), games, registry)
+    }
+
+    #[test]
+    fn libraries_are_read_from_libraryfolders() {
+        let (root, _, registry) = library("libraries");
+
+        assert_eq!(registry.get_install_path().unwrap(), root.join("Steam"));
+        assert_eq!(
+            registry.get_library_folders().unwrap(),
+            vec![root.join("Steam/steamapps"), root.join("SteamLibrary/steamapps")]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn installed_apps_are_found_in_all_libraries() {
+        let (root, games, registry) = library("apps");
+        write(&games.join("steamapps/appmanifest_570.acf"), MANIFEST);
+        // Повреждённые и неполные манифесты пропускаются
+        write(&root.join("Steam/steamapps/appmanifest_1.acf"), "\"AppState\"\n{\n\t\"appid\"");
+        write(
+            &root.join("Steam/steamapps/appmanifest_2.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"2\"\n}\n",
+        );
+
+        let apps = registry.get_installed_apps().unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].manifest.app_id, 570);
+        assert_eq!(apps[0].manifest.name, "Dota 2");
+        assert_eq!(apps[0].library, root.join("SteamLibrary/steamapps"));
+        assert_eq!(apps[0].install_path, root.join("SteamLibrary/steamapps/common/dota 2 beta"));
+
+        assert_eq!(
+            registry.get_installed_games().unwrap(),
+            vec![("570".to_string(), root.join("SteamLibrary/steamapps/common/dota 2 beta"))]
+        );
+        assert_eq!(registry.find_app_manifest(570).unwrap().unwrap().build_id, 12500000);
+        assert!(registry.find_app_manifest(730).unwrap().is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Библиотека на отключённом диске не мешает чтению остальных
+    #[test]
+    fn missing_library_is_skipped() {
+        let (root, games, registry) = library("missing-library");
+        std::fs::remove_dir_all(&games).unwrap();
+
+        assert_eq!(registry.get_library_folders().unwrap().len(), 2);
+        assert!(registry.get_installed_apps().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn steam_without_registry_is_not_found() {
+        let registry = SteamRegistry::with_backend(MemoryRegistry::new());
+        assert!(registry.get_install_path().is_err());
+        assert!(registry.get_installed_apps().is_err());
+    }
+
+    #[test]
+    fn app_info_index_is_rebuilt_when_file_changes() {
+        let steam = temp_dir("appinfo");
+        let path = steam.join("appcache/appinfo.vdf");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, APPINFO).unwrap();
+        let registry = registry(&steam);
+
+        let info = registry.get_app_info(570).unwrap().unwrap();
+        assert_eq!(info.name(), Some("Dota 2"));
+        assert_eq!(info.build_id("public"), Some(12500000));
+        assert_eq!(registry.get_app_info(730).unwrap().unwrap().build_id("public"), Some(13000000));
+        assert!(registry.get_app_info(440).unwrap().is_none());
+
+        // Клиент Steam получил новую сборку и переписал кэш
+        let updated: Vec<u8> = APPINFO
+            .windows(8)
+            .position(|w| w == b"12500000")
+            .map(|at| [&APPINFO[..at], b"12600000", &APPINFO[at + 8..]].concat())
+            .unwrap();
+        std::fs::write(&path, updated).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now() + std::time::Duration::from_secs(60)))
+            .unwrap();
+
+        assert_eq!(registry.get_app_info(570).unwrap().unwrap().build_id("public"), Some(12600000));
+
+        std::fs::remove_dir_all(&steam).unwrap();
     }
 }
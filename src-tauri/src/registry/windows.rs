@@ -0,0 +1,36 @@
+use super::RegistryBackend;
+use crate::error::{Result, Error};
+use winreg::enums::*;
+use winreg::RegKey;
+
+pub struct WindowsRegistry {
+    hklm: RegKey,
+}
+
+impl WindowsRegistry {
+    pub fn new() -> Self {
+        Self {
+            hklm: RegKey::predef(HKEY_LOCAL_MACHINE),
+        }
+    }
+
+    pub fn open_key(&self, path: &str) -> Result<RegKey> {
+        self.hklm
+            .open_subkey(path)
+            .map_err(|e| Error::Registry(e.to_string()))
+    }
+}
+
+impl Default for WindowsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegistryBackend for WindowsRegistry {
+    fn get_value(&self, path: &str, name: &str) -> Result<String> {
+        self.open_key(path)?
+            .get_value(name)
+            .map_err(|e| Error::Registry(e.to_string()))
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Sqlite, Row};
+use crate::error::{Result, Error};
+
+const LAST_UPDATE_RUN: &str = "last_update_run";
+
+// Таблица создаётся миграцией, см. migrations
+pub async fn load_last_update_run(pool: &Pool<Sqlite>) -> Result<Option<DateTime<Utc>>> {
+    let row = sqlx::query("SELECT value FROM scheduler_state WHERE key = ?")
+        .bind(LAST_UPDATE_RUN)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(row.and_then(|row| DateTime::from_timestamp(row.get(0), 0)))
+}
+
+pub async fn save_last_update_run(pool: &Pool<Sqlite>, time: DateTime<Utc>) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO scheduler_state (key, value) VALUES (?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(LAST_UPDATE_RUN)
+    .bind(time.timestamp())
+    .execute(pool)
+    .await
+    .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use super::*;
+
+    #[tokio::test]
+    async fn last_update_run_is_persisted() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::migrations::run(&pool, Path::new("memory.db")).await.unwrap();
+        assert_eq!(load_last_update_run(&pool).await.unwrap(), None);
+
+        let first = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let second = DateTime::from_timestamp(1_700_086_400, 0).unwrap();
+        save_last_update_run(&pool, first).await.unwrap();
+        save_last_update_run(&pool, second).await.unwrap();
+        assert_eq!(load_last_update_run(&pool).await.unwrap(), Some(second));
+    }
+}
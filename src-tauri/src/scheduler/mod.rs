@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tauri::{AppHandle, Emitter};
+use tokio::runtime::Runtime;
+use tokio::sync::{watch, Notify};
+use tokio::time::Instant;
+use crate::error::Result;
+use crate::games::{Game, GameManager, Platform};
+use crate::queue::{QueueManager, QueueOrigin, QueueStatus};
+use crate::settings::Settings;
+use crate::steam::BuildComparison;
+use crate::{log_error, log_info, log_warn};
+
+mod db;
+mod window;
+
+pub use window::{MaintenanceWindow, WindowSpan};
+
+// Первая проверка после запуска, когда steamcmd и список игр уже доступны
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Schedule {
+    auto_update: bool,
+    check_interval: Duration,
+    update_interval: chrono::Duration,
+    windows: Vec<MaintenanceWindow>,
+    pause_outside_windows: bool,
+}
+
+impl Schedule {
+    // check_interval в минутах, update_interval в часах
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            auto_update: settings.auto_update,
+            check_interval: Duration::from_secs(settings.check_interval.max(1) as u64 * 60),
+            update_interval: chrono::Duration::hours(settings.update_interval.max(0)),
+            windows: settings.maintenance_windows.clone(),
+            pause_outside_windows: settings.pause_outside_windows,
+        }
+    }
+}
+
+// Полезная нагрузка события updates-available
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableUpdate {
+    pub game_id: String,
+    pub name: String,
+    pub platform: Platform,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchedulerStatus {
+    pub auto_update: bool,
+    pub checking: bool,
+    pub last_check: Option<DateTime<Utc>>,
+    pub next_check: Option<DateTime<Utc>>,
+    pub last_update_run: Option<DateTime<Utc>>,
+    pub next_update_run: Option<DateTime<Utc>>,
+    pub window_open: bool,
+    // Текущее окно обслуживания или ближайшее следующее
+    pub next_window: Option<WindowSpan>,
+    pub available: Vec<AvailableUpdate>,
+    pub last_error: Option<String>,
+}
+
+// Периодическая проверка обновлений. Найденные обновления ставятся в очередь,
+// если включено auto_update, не чаще раза в update_interval и только внутри
+// окна обслуживания. Ручные обновления окна не ограничивают
+#[derive(Clone)]
+pub struct Scheduler {
+    pool: Arc<Pool<Sqlite>>,
+    games: GameManager,
+    queue: QueueManager,
+    app: Arc<AppHandle>,
+    schedule: Arc<watch::Sender<Schedule>>,
+    status: Arc<Mutex<SchedulerStatus>>,
+    trigger: Arc<Notify>,
+}
+
+impl Scheduler {
+    // Время последнего автоматического запуска хранится в settings.db,
+    // чтобы update_interval соблюдался и после перезапуска приложения
+    pub async fn new(
+        pool: Arc<Pool<Sqlite>>,
+        settings: &Settings,
+        games: GameManager,
+        queue: QueueManager,
+        app: AppHandle,
+    ) -> Result<Self> {
+        let schedule = Schedule::from_settings(settings);
+        let last_update_run = db::load_last_update_run(&pool).await?;
+        let status = SchedulerStatus {
+            auto_update: schedule.auto_update,
+            last_update_run,
+            next_update_run: next_update_run(&schedule, last_update_run, Utc::now()),
+            ..Default::default()
+        };
+
+        Ok(Self {
+            pool,
+            games,
+            queue,
+            app: Arc::new(app),
+            schedule: Arc::new(watch::channel(schedule).0),
+            status: Arc::new(Mutex::new(status)),
+            trigger: Arc::new(Notify::new()),
+        })
+    }
+
+    pub fn start(&self, runtime: &Runtime) {
+        runtime.spawn(self.clone().run());
+    }
+
+    // Пересчитывает расписание после сохранения настроек
+    pub fn apply_settings(&self, settings: &Settings) {
+        let schedule = Schedule::from_settings(settings);
+        self.status.lock().auto_update = schedule.auto_update;
+        self.schedule.send_if_modified(|current| {
+            let changed = *current != schedule;
+            *current = schedule;
+            changed
+        });
+    }
+
+    pub fn check_now(&self) {
+        self.trigger.notify_one();
+    }
+
+    pub fn status(&self) -> SchedulerStatus {
+        self.status.lock().clone()
+    }
+
+    async fn run(self) {
+        let mut schedule = self.schedule.subscribe();
+        let mut last_check: Option<Instant> = None;
+        let mut next_check = Instant::now() + STARTUP_DELAY;
+
+        loop {
+            self.status.lock().next_check = Some(to_utc(next_check));
+            let (was_open, boundary) = self.refresh_window(Utc::now());
+            let window_change = async {
+                match boundary {
+                    Some(boundary) => tokio::time::sleep_until(to_instant(boundary)).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(next_check) => {}
+                _ = self.trigger.notified() => {}
+                _ = window_change => {
+                    // Открытие окна сразу запускает проверку
+                    let (open, _) = self.refresh_window(Utc::now());
+                    if open == was_open {
+                        continue;
+                    }
+                    if !open {
+                        self.window_closed().await;
+                        continue;
+                    }
+                }
+                changed = schedule.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    // Новый интервал отсчитывается от последней проверки
+                    let interval = schedule.borrow_and_update().check_interval;
+                    if let Some(last) = last_check {
+                        next_check = (last + interval).max(Instant::now());
+                    }
+                    continue;
+                }
+            }
+
+            last_check = Some(Instant::now());
+            self.check().await;
+            next_check = Instant::now() + self.schedule.borrow().check_interval;
+        }
+    }
+
+    async fn check(&self) {
+        log_info!("Checking games for updates");
+        self.status.lock().checking = true;
+
+        let result = self.find_updates().await;
+        let now = Utc::now();
+
+        let available = {
+            let mut status = self.status.lock();
+            status.checking = false;
+            status.last_check = Some(now);
+
+            match result {
+                Ok(available) => {
+                    status.last_error = None;
+                    status.available = available.clone();
+                    available
+                }
+                Err(e) => {
+                    log_error!("Scheduled update check failed: {}", e);
+                    status.last_error = Some(e.to_string());
+                    return;
+                }
+            }
+        };
+
+        if !available.is_empty() {
+            log_info!("Updates available for {} games", available.len());
+            if let Err(e) = self.app.emit("updates-available", &available) {
+                log_error!("Failed to emit updates-available: {}", e);
+            }
+        }
+
+        let schedule = self.schedule.borrow().clone();
+        if schedule.auto_update {
+            self.run_updates(&schedule, &available, now).await;
+        } else {
+            self.status.lock().next_update_run = None;
+        }
+    }
+
+    async fn run_updates(&self, schedule: &Schedule, available: &[AvailableUpdate], now: DateTime<Utc>) {
+        let last_run = self.status.lock().last_update_run;
+        let due = last_run.is_none_or(|last| now - last >= schedule.update_interval);
+        if !due || available.is_empty() || !window::is_open(&schedule.windows, now) {
+            self.status.lock().next_update_run = next_update_run(schedule, last_run, now);
+            return;
+        }
+
+        for update in available {
+            if let Err(e) = self.queue.enqueue(&update.game_id, 0, QueueOrigin::Auto).await {
+                log_warn!("Failed to enqueue update of {}: {}", update.game_id, e);
+            }
+        }
+
+        if let Err(e) = db::save_last_update_run(&self.pool, now).await {
+            log_error!("Failed to save last automatic update time: {}", e);
+        }
+        let mut status = self.status.lock();
+        status.last_update_run = Some(now);
+        status.next_update_run = next_update_run(schedule, Some(now), now);
+    }
+
+    // Отражает окно в статусе. Возвращает, открыто ли оно, и когда это изменится
+    fn refresh_window(&self, now: DateTime<Utc>) -> (bool, Option<DateTime<Utc>>) {
+        let schedule = self.schedule.borrow();
+        let span = window::next_window(&schedule.windows, now);
+        let open = schedule.windows.is_empty() || span.is_some_and(|span| span.contains(now));
+
+        let mut status = self.status.lock();
+        status.window_open = open;
+        status.next_window = span;
+
+        let boundary = span
+            .filter(|_| schedule.auto_update)
+            .map(|span| if span.contains(now) { span.end } else { span.start });
+        (open, boundary)
+    }
+
+    // Незапущенные автоматические обновления снимаются с очереди, запущенные
+    // приостанавливаются, если это включено, и остаются в очереди. В следующем
+    // окне проверка поставит их снова, и steamcmd продолжит загрузку
+    async fn window_closed(&self) {
+        log_info!("Maintenance window closed");
+        let pause = self.schedule.borrow().pause_outside_windows;
+
+        let entries = match self.queue.list().await {
+            Ok(entries) => entries,
+            Err(e) => {
+                log_error!("Failed to read update queue: {}", e);
+                return;
+            }
+        };
+
+        for entry in entries.iter().filter(|entry| entry.origin == QueueOrigin::Auto) {
+            let result = match entry.status {
+                QueueStatus::Queued => self.queue.remove(&entry.game_id).await.map(|_| ()),
+                QueueStatus::Running if pause => self.games.pause_update(&entry.game_id),
+                QueueStatus::Running | QueueStatus::Paused => Ok(()),
+            };
+            if let Err(e) = result {
+                log_warn!("Failed to stop automatic update of {}: {}", entry.game_id, e);
+            }
+        }
+    }
+
+    async fn find_updates(&self) -> Result<Vec<AvailableUpdate>> {
+        let games = self.games.installed_games().await?;
+
+        // Сборки всех игр Steam сверяются одним запросом к steamcmd
+        let steam_ids: Vec<String> = games
+            .iter()
+            .filter(|game| game.platform == Platform::Steam)
+            .map(|game| game.id.clone())
+            .collect();
+        let mut comparisons = match steam_ids.is_empty() {
+            true => HashMap::new(),
+            false => self
+                .games
+                .compare_game_builds_batch(&steam_ids)
+                .await
+                .inspect_err(|e| log_warn!("Build comparison failed, using manifests: {}", e))
+                .unwrap_or_default(),
+        };
+
+        let mut available = Vec::new();
+        for game in games {
+            match self.needs_update(&game, comparisons.remove(&game.id)).await {
+                Ok(true) => available.push(AvailableUpdate {
+                    game_id: game.id,
+                    name: game.name,
+                    platform: game.platform,
+                }),
+                Ok(false) => {}
+                Err(e) => log_warn!("Failed to check updates for {}: {}", game.id, e),
+            }
+        }
+        Ok(available)
+    }
+
+    // Для Steam сверяем сборку с сервером, манифест сообщает об обновлении
+    // только если его уже обнаружил клиент Steam
+    async fn needs_update(&self, game: &Game, comparison: Option<Result<BuildComparison>>) -> Result<bool> {
+        match comparison {
+            Some(Ok(comparison)) => return Ok(comparison.needs_update),
+            Some(Err(e)) => log_warn!("Build comparison for {} failed, using manifest: {}", game.id, e),
+            None => {}
+        }
+        Ok(self.games.check_game_updates(&game.id).await?.needs_update)
+    }
+}
+
+// Когда планировщик сможет запустить обновления: по update_interval и окнам
+fn next_update_run(schedule: &Schedule, last_run: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if !schedule.auto_update {
+        return None;
+    }
+
+    let due = last_run.map_or(now, |last| (last + schedule.update_interval).max(now));
+    if schedule.windows.is_empty() {
+        return Some(due);
+    }
+    window::next_window(&schedule.windows, due).map(|span| span.start.max(due))
+}
+
+fn to_instant(time: DateTime<Utc>) -> Instant {
+    Instant::now() + (time - Utc::now()).to_std().unwrap_or_default()
+}
+
+fn to_utc(instant: Instant) -> DateTime<Utc> {
+    let remaining = instant.saturating_duration_since(Instant::now());
+    Utc::now() + chrono::Duration::from_std(remaining).unwrap_or_default()
+}
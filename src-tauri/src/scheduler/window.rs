@@ -0,0 +1,363 @@
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, Duration, Local, LocalResult, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::log_warn;
+
+// Окно, в которое разрешено запускать автоматические обновления.
+// Без timezone время считается по местному часовому поясу
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MaintenanceWindow {
+    // Если end раньше start, окно заканчивается на следующий день
+    Weekly {
+        days: Vec<Weekday>,
+        start: NaiveTime,
+        end: NaiveTime,
+        #[serde(default)]
+        timezone: Option<String>,
+    },
+    // Окно открывается по cron-выражению (5 или 6 полей) на duration_minutes
+    Cron {
+        expression: String,
+        duration_minutes: u32,
+        #[serde(default)]
+        timezone: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WindowSpan {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl WindowSpan {
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+impl MaintenanceWindow {
+    // Текущий интервал окна или ближайший следующий
+    pub fn next_span(&self, now: DateTime<Utc>) -> Result<Option<WindowSpan>> {
+        match self.timezone() {
+            None => self.next_span_in(&Local, now),
+            Some(name) => {
+                let tz = Tz::from_str(name)
+                    .map_err(|_| Error::ConfigError(format!("Unknown timezone: {}", name)))?;
+                self.next_span_in(&tz, now)
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.next_span(Utc::now()).map(|_| ())
+    }
+
+    fn timezone(&self) -> Option<&str> {
+        match self {
+            Self::Weekly { timezone, .. } | Self::Cron { timezone, .. } => timezone.as_deref(),
+        }
+    }
+
+    fn next_span_in<Z: TimeZone>(&self, tz: &Z, now: DateTime<Utc>) -> Result<Option<WindowSpan>> {
+        match self {
+            Self::Weekly { days, start, end, .. } => Ok(weekly_span(tz, days, *start, *end, now)),
+            Self::Cron { expression, duration_minutes, .. } => {
+                cron_span(tz, expression, *duration_minutes, now)
+            }
+        }
+    }
+}
+
+// Ближайший интервал среди всех окон; ошибочные окна пропускаются
+pub fn next_window(windows: &[MaintenanceWindow], now: DateTime<Utc>) -> Option<WindowSpan> {
+    windows
+        .iter()
+        .filter_map(|window| {
+            window
+                .next_span(now)
+                .unwrap_or_else(|e| {
+                    log_warn!("Ignoring invalid maintenance window: {}", e);
+                    None
+                })
+        })
+        .min_by_key(|span| span.start)
+}
+
+// Без окон ограничений нет
+pub fn is_open(windows: &[MaintenanceWindow], now: DateTime<Utc>) -> bool {
+    windows.is_empty() || next_window(windows, now).is_some_and(|span| span.contains(now))
+}
+
+fn weekly_span<Z: TimeZone>(
+    tz: &Z,
+    days: &[Weekday],
+    start: NaiveTime,
+    end: NaiveTime,
+    now: DateTime<Utc>,
+) -> Option<WindowSpan> {
+    let today = now.with_timezone(tz).date_naive();
+
+    // Начинаем со вчерашнего дня: окно через полночь может быть ещё открыто
+    (-1..=7)
+        .map(|offset| today + Duration::days(offset))
+        .filter(|date| days.contains(&date.weekday()))
+        .filter_map(|date| {
+            let end_date = if end <= start { date.succ_opt()? } else { date };
+            Some(WindowSpan {
+                start: resolve_local(tz, date.and_time(start), false)?,
+                end: resolve_local(tz, end_date.and_time(end), true)?,
+            })
+        })
+        .find(|span| span.end > now)
+}
+
+// Местное время в момент UTC. Повторяющийся при переводе часов назад час
+// даёт раннее или позднее вхождение; время в пропущенном часе сдвигается
+// вперёд на длину перехода (02:30 при переходе 02:00 -> 03:00 становится 03:30),
+// иначе окно в день перевода часов пропало бы
+fn resolve_local<Z: TimeZone>(tz: &Z, time: NaiveDateTime, latest: bool) -> Option<DateTime<Utc>> {
+    match tz.from_local_datetime(&time) {
+        LocalResult::Single(time) => Some(time.with_timezone(&Utc)),
+        LocalResult::Ambiguous(early, late) => Some(if latest { late } else { early }.with_timezone(&Utc)),
+        LocalResult::None => {
+            let before = tz.from_local_datetime(&(time - Duration::days(1))).earliest()?;
+            let offset = Duration::seconds(before.offset().fix().local_minus_utc() as i64);
+            Some(Utc.from_utc_datetime(&(time - offset)))
+        }
+    }
+}
+
+fn cron_span<Z: TimeZone>(
+    tz: &Z,
+    expression: &str,
+    duration_minutes: u32,
+    now: DateTime<Utc>,
+) -> Result<Option<WindowSpan>> {
+    let expression = match expression.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [minute, hour, day, month, weekday] => format!(
+            "0 {} {} {} {} {}",
+            minute,
+            hour,
+            day,
+            month,
+            crontab_weekdays(weekday)?
+        ),
+        _ => expression.to_string(),
+    };
+    let schedule = cron::Schedule::from_str(&expression)
+        .map_err(|e| Error::ConfigError(format!("Invalid cron expression '{}': {}", expression, e)))?;
+
+    // Окно, начавшееся не позже duration назад, ещё открыто
+    let duration = Duration::minutes(duration_minutes as i64);
+    let from = (now - duration).with_timezone(tz);
+
+    Ok(schedule
+        .after(&from)
+        .map(|start| {
+            let start = start.with_timezone(&Utc);
+            WindowSpan { start, end: start + duration }
+        })
+        .find(|span| span.end > now))
+}
+
+// Выражение из 5 полей понимается как crontab: дни недели 0-7, воскресенье
+// 0 или 7. Крейт cron ожидает поле секунд первым и считает дни с 1
+// (воскресенье), поэтому числа сдвигаются. Выражение из 6 полей передаётся
+// крейту как есть. Названия дней (MON-FRI) одинаковы в обоих форматах
+fn crontab_weekdays(field: &str) -> Result<String> {
+    let invalid = || Error::ConfigError(format!("Invalid day of week in cron expression: '{}'", field));
+    let weekday = |value: &str| -> Result<Option<u32>> {
+        match value.parse::<u32>() {
+            Ok(day) if day <= 7 => Ok(Some(day)),
+            Ok(_) => Err(invalid()),
+            Err(_) => Ok(None),
+        }
+    };
+
+    let mut items = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, format!("/{}", step)),
+            None => (item, String::new()),
+        };
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (weekday(first)?, Some(weekday(last)?)),
+            None => (weekday(range)?, None),
+        };
+
+        let translated = match (first, last) {
+            (None, None | Some(None)) => item.to_string(),
+            (Some(day), None) => format!("{}{}", day % 7 + 1, step),
+            (Some(0), Some(Some(7))) => format!("1-7{}", step),
+            // 5-7 (пятница - воскресенье) после сдвига заканчивается на 1
+            (Some(first), Some(Some(7))) if first < 7 && step.is_empty() => format!("{}-7,1", first + 1),
+            (Some(first), Some(Some(last))) if first <= last && last < 7 => {
+                format!("{}-{}{}", first + 1, last + 1, step)
+            }
+            // Число вместе с названием дня в одном диапазоне
+            _ => return Err(invalid()),
+        };
+        items.push(translated);
+    }
+    Ok(items.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn time(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+    }
+
+    fn weekly(days: &[Weekday], start: &str, end: &str, timezone: &str) -> MaintenanceWindow {
+        MaintenanceWindow::Weekly {
+            days: days.to_vec(),
+            start: time(start),
+            end: time(end),
+            timezone: Some(timezone.to_string()),
+        }
+    }
+
+    fn cron(expression: &str, duration_minutes: u32, timezone: &str) -> MaintenanceWindow {
+        MaintenanceWindow::Cron {
+            expression: expression.to_string(),
+            duration_minutes,
+            timezone: Some(timezone.to_string()),
+        }
+    }
+
+    fn span(start: &str, end: &str) -> Option<WindowSpan> {
+        Some(WindowSpan { start: utc(start), end: utc(end) })
+    }
+
+    #[test]
+    fn overnight_window_is_open_after_midnight() {
+        // Пятница 22:00 - суббота 02:00
+        let window = weekly(&[Weekday::Fri], "22:00", "02:00", "UTC");
+        let expected = span("2024-06-07T22:00:00Z", "2024-06-08T02:00:00Z");
+
+        assert_eq!(window.next_span(utc("2024-06-07T12:00:00Z")).unwrap(), expected);
+        assert_eq!(window.next_span(utc("2024-06-08T01:30:00Z")).unwrap(), expected);
+        assert!(is_open(std::slice::from_ref(&window), utc("2024-06-08T01:30:00Z")));
+        assert!(!is_open(std::slice::from_ref(&window), utc("2024-06-08T02:00:00Z")));
+
+        // После закрытия - через неделю
+        assert_eq!(
+            window.next_span(utc("2024-06-08T02:00:00Z")).unwrap(),
+            span("2024-06-14T22:00:00Z", "2024-06-15T02:00:00Z")
+        );
+    }
+
+    #[test]
+    fn window_starting_in_dst_gap_is_shifted() {
+        // 31 марта 2024 в Берлине часы переводятся с 02:00 на 03:00
+        let window = weekly(&[Weekday::Sun], "02:30", "05:00", "Europe/Berlin");
+        assert_eq!(
+            window.next_span(utc("2024-03-30T12:00:00Z")).unwrap(),
+            span("2024-03-31T01:30:00Z", "2024-03-31T03:00:00Z")
+        );
+    }
+
+    #[test]
+    fn window_ending_in_dst_gap_is_shifted() {
+        let window = weekly(&[Weekday::Sun], "01:00", "02:30", "Europe/Berlin");
+        assert_eq!(
+            window.next_span(utc("2024-03-30T12:00:00Z")).unwrap(),
+            span("2024-03-31T00:00:00Z", "2024-03-31T01:30:00Z")
+        );
+    }
+
+    #[test]
+    fn window_in_repeated_hour_uses_widest_span() {
+        // 27 октября 2024 час 02:00-03:00 в Берлине повторяется
+        let window = weekly(&[Weekday::Sun], "02:15", "02:45", "Europe/Berlin");
+        assert_eq!(
+            window.next_span(utc("2024-10-26T12:00:00Z")).unwrap(),
+            span("2024-10-27T00:15:00Z", "2024-10-27T01:45:00Z")
+        );
+    }
+
+    #[test]
+    fn cron_window_is_open_for_duration() {
+        let window = cron("0 3 * * *", 90, "UTC");
+        let expected = span("2024-06-07T03:00:00Z", "2024-06-07T04:30:00Z");
+
+        assert_eq!(window.next_span(utc("2024-06-07T01:00:00Z")).unwrap(), expected);
+        assert_eq!(window.next_span(utc("2024-06-07T04:00:00Z")).unwrap(), expected);
+        assert_eq!(
+            window.next_span(utc("2024-06-07T04:30:00Z")).unwrap(),
+            span("2024-06-08T03:00:00Z", "2024-06-08T04:30:00Z")
+        );
+    }
+
+    #[test]
+    fn cron_window_uses_timezone() {
+        // Шесть полей, с секундами; 03:00 по Москве - 00:00 UTC
+        let window = cron("0 0 3 * * Sat", 60, "Europe/Moscow");
+        assert_eq!(
+            window.next_span(utc("2024-06-07T12:00:00Z")).unwrap(),
+            span("2024-06-08T00:00:00Z", "2024-06-08T01:00:00Z")
+        );
+    }
+
+    #[test]
+    fn crontab_weekdays_start_from_sunday() {
+        // 7 июня 2024 - пятница
+        let weekdays = cron("0 9 * * 1-5", 60, "UTC");
+        assert_eq!(
+            weekdays.next_span(utc("2024-06-07T12:00:00Z")).unwrap(),
+            span("2024-06-10T09:00:00Z", "2024-06-10T10:00:00Z")
+        );
+
+        for sunday in ["0 3 * * 0", "0 3 * * 7", "0 3 * * SUN", "0 3 * * 5-7"] {
+            let window = cron(sunday, 60, "UTC");
+            assert_eq!(
+                window.next_span(utc("2024-06-08T12:00:00Z")).unwrap(),
+                span("2024-06-09T03:00:00Z", "2024-06-09T04:00:00Z"),
+                "{}",
+                sunday
+            );
+        }
+    }
+
+    #[test]
+    fn crontab_weekday_field_is_translated() {
+        assert_eq!(crontab_weekdays("*").unwrap(), "*");
+        assert_eq!(crontab_weekdays("1-5").unwrap(), "2-6");
+        assert_eq!(crontab_weekdays("0,6").unwrap(), "1,7");
+        assert_eq!(crontab_weekdays("*/2").unwrap(), "*/2");
+        assert_eq!(crontab_weekdays("0-6/2").unwrap(), "1-7/2");
+        assert_eq!(crontab_weekdays("5-7").unwrap(), "6-7,1");
+        assert_eq!(crontab_weekdays("0-7").unwrap(), "1-7");
+        assert_eq!(crontab_weekdays("SAT,0").unwrap(), "SAT,1");
+        assert_eq!(crontab_weekdays("MON-FRI").unwrap(), "MON-FRI");
+        assert!(crontab_weekdays("8").is_err());
+        assert!(crontab_weekdays("1-SUN").is_err());
+        assert!(crontab_weekdays("5-7/2").is_err());
+    }
+
+    #[test]
+    fn invalid_windows_are_reported_and_skipped() {
+        assert!(cron("not a cron", 60, "UTC").validate().is_err());
+        assert!(weekly(&[Weekday::Mon], "01:00", "02:00", "Mars/Olympus").validate().is_err());
+
+        let valid = cron("0 3 * * *", 60, "UTC");
+        let windows = [cron("not a cron", 60, "UTC"), valid.clone()];
+        let now = utc("2024-06-07T01:00:00Z");
+        assert_eq!(next_window(&windows, now), valid.next_span(now).unwrap());
+    }
+
+    #[test]
+    fn no_windows_means_always_open() {
+        assert!(is_open(&[], utc("2024-06-07T01:00:00Z")));
+    }
+}
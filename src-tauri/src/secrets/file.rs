@@ -0,0 +1,372 @@
+use super::SecretBackend;
+use crate::error::{Error, Result};
+use crate::log_warn;
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8] = b"UIOVAULT";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+const KEY_LEN: usize = 32;
+
+const PASSPHRASE_ENV: &str = "UPDATEIO_VAULT_PASSPHRASE";
+
+// Откуда берётся парольная фраза хранилища
+enum KeySource {
+    Passphrase(String),
+    // Случайный ключ рядом с хранилищем. В Windows он зашифрован DPAPI
+    // и расшифровывается только под текущим пользователем, в остальных
+    // системах защищён лишь правами 0600 на файл
+    KeyFile(PathBuf),
+}
+
+// Формат: MAGIC | версия | соль | nonce | ChaCha20-Poly1305(JSON),
+// ключ выводится из парольной фразы через Argon2id
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    key: KeySource,
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileStore {
+    // Парольная фраза из переменной окружения, иначе ключ в файле <vault>.key
+    pub fn new(path: PathBuf) -> Self {
+        if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) {
+            return Self::with_passphrase(path, passphrase);
+        }
+
+        let key_path = path.with_extension("key");
+        if !cfg!(windows) {
+            log_warn!(
+                "{} is not set: the secrets vault key is kept in {} and protected only by file permissions (0600). \
+                 Anyone who can read this file as your user can decrypt stored passwords",
+                PASSPHRASE_ENV,
+                key_path.display()
+            );
+        }
+        Self {
+            path,
+            key: KeySource::KeyFile(key_path),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn with_passphrase(path: PathBuf, passphrase: impl Into<String>) -> Self {
+        Self {
+            path,
+            key: KeySource::Passphrase(passphrase.into()),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn passphrase(&self) -> Result<String> {
+        match &self.key {
+            KeySource::Passphrase(passphrase) => Ok(passphrase.clone()),
+            KeySource::KeyFile(path) => load_or_create_key(path),
+        }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(Error::SecretStore(format!("Failed to read vault: {}", e))),
+        };
+
+        if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+            return Err(Error::SecretStore("Vault file is corrupted".to_string()));
+        }
+        if data[MAGIC.len()] != VERSION {
+            return Err(Error::SecretStore(format!(
+                "Unsupported vault version {}",
+                data[MAGIC.len()]
+            )));
+        }
+
+        decrypt(&data, &self.passphrase()?)?.ok_or_else(|| {
+            Error::SecretStore("Failed to decrypt vault: wrong passphrase or corrupted file".to_string())
+        })
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let plaintext = serde_json::to_vec(secrets)
+            .map_err(|e| Error::SecretStore(e.to_string()))?;
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher(&self.passphrase()?, &salt)?
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| Error::SecretStore("Failed to encrypt vault".to_string()))?;
+
+        let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| Error::SecretStore(format!("Failed to create vault directory: {}", e)))?;
+        }
+
+        // Запись через временный файл, чтобы не потерять хранилище при сбое
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, &data)
+            .map_err(|e| Error::SecretStore(format!("Failed to write vault: {}", e)))?;
+        restrict_permissions(&tmp);
+        std::fs::rename(&tmp, &self.path)
+            .map_err(|e| Error::SecretStore(format!("Failed to write vault: {}", e)))
+    }
+}
+
+// None, если ключ не подошёл
+fn decrypt(data: &[u8], passphrase: &str) -> Result<Option<BTreeMap<String, String>>> {
+    let salt = &data[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = Nonce::from_slice(&data[HEADER_LEN - NONCE_LEN..HEADER_LEN]);
+    let plaintext = match cipher(passphrase, salt)?.decrypt(nonce, &data[HEADER_LEN..]) {
+        Ok(plaintext) => plaintext,
+        Err(_) => return Ok(None),
+    };
+
+    serde_json::from_slice(&plaintext)
+        .map(Some)
+        .map_err(|e| Error::SecretStore(format!("Invalid vault contents: {}", e)))
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::SecretStore(format!("Key derivation failed: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+impl SecretBackend for EncryptedFileStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let _guard = self.lock.lock();
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        let _guard = self.lock.lock();
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), value.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let _guard = self.lock.lock();
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+// Ключ создаётся при первом обращении. create_new не даёт перезаписать
+// ключ, уже созданный параллельно
+fn load_or_create_key(path: &Path) -> Result<String> {
+    match std::fs::read(path) {
+        Ok(data) => return unprotect_key(&data).map(|key| to_hex(&key)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(Error::SecretStore(format!("Failed to read vault key: {}", e))),
+    }
+
+    let mut key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    let data = protect_key(&key)?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| Error::SecretStore(format!("Failed to create vault directory: {}", e)))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = match options.open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return load_or_create_key(path),
+        Err(e) => return Err(Error::SecretStore(format!("Failed to create vault key: {}", e))),
+    };
+    file.write_all(&data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| Error::SecretStore(format!("Failed to write vault key: {}", e)))?;
+
+    Ok(to_hex(&key))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(windows)]
+fn protect_key(key: &[u8]) -> Result<Vec<u8>> {
+    dpapi::protect(key)
+}
+
+#[cfg(windows)]
+fn unprotect_key(data: &[u8]) -> Result<Vec<u8>> {
+    dpapi::unprotect(data)
+}
+
+#[cfg(not(windows))]
+fn protect_key(key: &[u8]) -> Result<Vec<u8>> {
+    Ok(key.to_vec())
+}
+
+#[cfg(not(windows))]
+fn unprotect_key(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() != KEY_LEN {
+        return Err(Error::SecretStore("Vault key file is corrupted".to_string()));
+    }
+    Ok(data.to_vec())
+}
+
+// CryptProtectData привязывает данные к учётной записи Windows
+#[cfg(windows)]
+mod dpapi {
+    use crate::error::{Error, Result};
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    pub fn protect(data: &[u8]) -> Result<Vec<u8>> {
+        let input = blob(data);
+        let mut output = CRYPT_INTEGER_BLOB { cbData: 0, pbData: std::ptr::null_mut() };
+        let ok = unsafe {
+            CryptProtectData(
+                &input,
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        };
+        if ok == 0 {
+            return Err(Error::SecretStore(format!(
+                "CryptProtectData failed: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(take(output))
+    }
+
+    pub fn unprotect(data: &[u8]) -> Result<Vec<u8>> {
+        let input = blob(data);
+        let mut output = CRYPT_INTEGER_BLOB { cbData: 0, pbData: std::ptr::null_mut() };
+        let ok = unsafe {
+            CryptUnprotectData(
+                &input,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        };
+        if ok == 0 {
+            return Err(Error::SecretStore(format!(
+                "CryptUnprotectData failed: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(take(output))
+    }
+
+    fn blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+        CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            pbData: data.as_ptr() as *mut u8,
+        }
+    }
+
+    // Буфер выделен системой через LocalAlloc
+    fn take(output: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let data = unsafe { std::slice::from_raw_parts(output.pbData, output.cbData as usize) }.to_vec();
+        unsafe { LocalFree(output.pbData as _) };
+        data
+    }
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &std::path::Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &std::path::Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("updateio-vault-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn key_file_store(dir: &Path) -> EncryptedFileStore {
+        EncryptedFileStore {
+            path: dir.join("secrets.vault"),
+            key: KeySource::KeyFile(dir.join("secrets.key")),
+            lock: Mutex::new(()),
+        }
+    }
+
+    #[test]
+    fn key_file_is_created_once_and_reused() {
+        let dir = temp_dir("key-file");
+        let store = key_file_store(&dir);
+        store.set("steam", "hunter2").unwrap();
+
+        let key = std::fs::read(dir.join("secrets.key")).unwrap();
+        #[cfg(not(windows))]
+        assert_eq!(key.len(), KEY_LEN);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join("secrets.key")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let reopened = key_file_store(&dir);
+        assert_eq!(reopened.get("steam").unwrap().as_deref(), Some("hunter2"));
+        assert_eq!(std::fs::read(dir.join("secrets.key")).unwrap(), key);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let dir = temp_dir("passphrase");
+        EncryptedFileStore::with_passphrase(dir.join("secrets.vault"), "correct horse")
+            .set("steam", "hunter2")
+            .unwrap();
+
+        let store = EncryptedFileStore::with_passphrase(dir.join("secrets.vault"), "battery staple");
+        assert!(matches!(store.get("steam"), Err(Error::SecretStore(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,87 @@
+mod file;
+mod os_keyring;
+
+pub use file::EncryptedFileStore;
+pub use os_keyring::KeyringStore;
+
+use crate::error::{Error, Result};
+use crate::log_warn;
+use std::path::Path;
+use std::sync::Arc;
+
+const KEYRING_PREFIX: &str = "keyring:";
+const FILE_PREFIX: &str = "file:";
+
+pub trait SecretBackend: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn set(&self, key: &str, value: &str) -> Result<()>;
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+// Хранилище секретов. В настройках сохраняется только ссылка вида
+// "keyring:<ключ>" или "file:<ключ>", сам секрет лежит в бэкенде
+#[derive(Clone)]
+pub struct SecretStore {
+    keyring: Option<Arc<dyn SecretBackend>>,
+    file: Arc<dyn SecretBackend>,
+}
+
+impl SecretStore {
+    // Системный keyring, если он доступен, иначе зашифрованный файл в data_dir
+    pub fn open(data_dir: &Path) -> Self {
+        let keyring = match KeyringStore::probe() {
+            Ok(store) => Some(Arc::new(store) as Arc<dyn SecretBackend>),
+            Err(e) => {
+                log_warn!("OS keyring is unavailable, using encrypted file: {}", e);
+                None
+            }
+        };
+
+        Self {
+            keyring,
+            file: Arc::new(EncryptedFileStore::new(data_dir.join("secrets.vault"))),
+        }
+    }
+
+    pub fn with_backends(keyring: Option<Arc<dyn SecretBackend>>, file: Arc<dyn SecretBackend>) -> Self {
+        Self { keyring, file }
+    }
+
+    // Сохраняет секрет и возвращает ссылку на него
+    pub fn store(&self, key: &str, value: &str) -> Result<String> {
+        if let Some(keyring) = &self.keyring {
+            match keyring.set(key, value) {
+                Ok(()) => return Ok(format!("{}{}", KEYRING_PREFIX, key)),
+                Err(e) => log_warn!("Failed to write to OS keyring, using encrypted file: {}", e),
+            }
+        }
+
+        self.file.set(key, value)?;
+        Ok(format!("{}{}", FILE_PREFIX, key))
+    }
+
+    pub fn resolve(&self, reference: &str) -> Result<Option<String>> {
+        let (backend, key) = self.backend(reference)?;
+        backend.get(key)
+    }
+
+    pub fn remove(&self, reference: &str) -> Result<()> {
+        let (backend, key) = self.backend(reference)?;
+        backend.delete(key)
+    }
+
+    fn backend<'a>(&self, reference: &'a str) -> Result<(&Arc<dyn SecretBackend>, &'a str)> {
+        if let Some(key) = reference.strip_prefix(KEYRING_PREFIX) {
+            let keyring = self.keyring.as_ref().ok_or_else(|| {
+                Error::SecretStore(format!("Secret {} is stored in the OS keyring, which is unavailable", key))
+            })?;
+            return Ok((keyring, key));
+        }
+
+        if let Some(key) = reference.strip_prefix(FILE_PREFIX) {
+            return Ok((&self.file, key));
+        }
+
+        Err(Error::SecretStore(format!("Invalid secret reference: {}", reference)))
+    }
+}
@@ -0,0 +1,48 @@
+use super::SecretBackend;
+use crate::error::{Error, Result};
+use keyring::Entry;
+
+const SERVICE: &str = "com.updateio.app";
+const PROBE_KEY: &str = "updateio-probe";
+
+// Credential Manager в Windows, Keychain в macOS, Secret Service в Linux
+pub struct KeyringStore;
+
+impl KeyringStore {
+    // Проверяет, что хранилище отвечает: отсутствие записи считается успехом
+    pub fn probe() -> Result<Self> {
+        match entry(PROBE_KEY)?.get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(Self),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+impl SecretBackend for KeyringStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        match entry(key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        entry(key)?.set_password(value).map_err(keyring_error)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        match entry(key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+fn entry(key: &str) -> Result<Entry> {
+    Entry::new(SERVICE, key).map_err(keyring_error)
+}
+
+fn keyring_error(e: keyring::Error) -> Error {
+    Error::SecretStore(format!("OS keyring: {}", e))
+}
@@ -1,16 +1,22 @@
-use sqlx::{Pool, Sqlite, Row};
+use sqlx::{Pool, Sqlite, SqliteConnection, Row};
 use directories::ProjectDirs;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use crate::error::{Result, Error};
-use crate::logging::LogRotation;
-use crate::log_info;
+use crate::migrations::{self, MigrationFuture};
+use crate::{log_info, log_warn};
 use super::Settings;
 
-pub async fn init_database() -> Result<Pool<Sqlite>> {
+pub fn data_dir() -> Result<PathBuf> {
     let project_dirs = ProjectDirs::from("com", "updateio", "app")
         .ok_or_else(|| Error::Database("Failed to get app data directory".to_string()))?;
+    Ok(project_dirs.data_local_dir().to_path_buf())
+}
 
-    let db_dir = project_dirs.data_local_dir();
-    std::fs::create_dir_all(db_dir)
+pub async fn init_database() -> Result<Pool<Sqlite>> {
+    let db_dir = data_dir()?;
+    std::fs::create_dir_all(&db_dir)
         .map_err(|e| Error::Database(format!("Failed to create data directory: {}", e)))?;
 
     let db_path = db_dir.join("settings.db");
@@ -29,166 +35,246 @@ pub async fn init_database() -> Result<Pool<Sqlite>> {
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
 
-    // Создаем таблицу, если её нет
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS settings (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await
-    .map_err(|e| Error::Database(e.to_string()))?;
+    migrations::run(&pool, &db_path).await?;
 
     Ok(pool)
 }
 
-pub async fn load_settings(pool: &Pool<Sqlite>) -> Result<Settings> {
-    let mut settings = Settings::default();
+// Версия формата значений в таблице settings
+pub const SETTINGS_VERSION: u64 = 1;
+
+// Ключ с паролем, который хранился открытым текстом; его забирает
+// SettingsManager при запуске
+const LEGACY_PASSWORD_KEY: &str = "steam_password";
 
-    let rows = sqlx::query("SELECT key, value FROM settings")
+pub async fn load_settings(pool: &Pool<Sqlite>) -> Result<Settings> {
+    let rows = sqlx::query("SELECT key, value FROM settings WHERE key != ?")
+        .bind(LEGACY_PASSWORD_KEY)
         .fetch_all(pool)
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
 
+    let mut document = Map::new();
     for row in rows {
         let key: String = row.get(0);
         let value: String = row.get(1);
-        match key.as_str() {
-            "auto_update" => settings.auto_update = value.parse().unwrap_or(false),
-            "notifications" => settings.notifications = value.parse().unwrap_or(true),
-            "check_interval" => settings.check_interval = value.parse().unwrap_or(30),
-            "steam_path" => settings.paths.steam = Some(value),
-            "epic_path" => settings.paths.epic = Some(value),
-            "cache_ttl_minutes" => settings.cache_ttl_minutes = value.parse().unwrap_or(30),
-            "cache_size" => settings.cache_size = value.parse().unwrap_or(1000),
-            "log_level" => settings.logging.level = value,
-            "log_file_name" => settings.logging.file_name = value,
-            "log_rotation" => {
-                settings.logging.rotation = match value.as_str() {
-                    "minutely" => LogRotation::Minutely,
-                    "hourly" => LogRotation::Hourly,
-                    "daily" => LogRotation::Daily,
-                    "never" => LogRotation::Never,
-                    _ => LogRotation::Daily,
-                }
-            },
-            "log_custom_path" => settings.logging.custom_path = Some(value.into()),
-            "custom_steamcmd_path" => settings.custom_steamcmd_path = Some(value.into()),
-            "steam_username" => settings.steam_username = Some(value),
-            "steam_password" => settings.steam_password = Some(value),
-            _ => {}
+        match serde_json::from_str(&value) {
+            Ok(value) => {
+                document.insert(key, value);
+            }
+            Err(e) => log_warn!("Setting {} is not valid JSON, using default: {}", key, e),
+        }
+    }
+
+    let version = document.remove("version").and_then(|v| v.as_u64()).unwrap_or(SETTINGS_VERSION);
+    if version > SETTINGS_VERSION {
+        log_warn!(
+            "Settings were saved by a newer version ({} > {}), unknown fields are kept as is",
+            version, SETTINGS_VERSION
+        );
+    }
+
+    Ok(settings_from_document(document))
+}
+
+// Поле с неподходящим значением получает значение по умолчанию, остальные
+// читаются как есть. В базе такое значение остаётся до следующего сохранения
+fn settings_from_document(document: Map<String, Value>) -> Settings {
+    if let Ok(settings) = serde_json::from_value(Value::Object(document.clone())) {
+        return settings;
+    }
+
+    let mut valid = Map::new();
+    for (key, value) in document {
+        let candidate = Map::from_iter([(key.clone(), value.clone())]);
+        match serde_json::from_value::<Settings>(Value::Object(candidate)) {
+            Ok(_) => {
+                valid.insert(key, value);
+            }
+            Err(e) => log_warn!("Invalid value of setting {}, using default: {}", key, e),
         }
     }
 
-    Ok(settings)
+    serde_json::from_value(Value::Object(valid)).unwrap_or_else(|e| {
+        log_warn!("Failed to read settings, using defaults: {}", e);
+        Settings::default()
+    })
 }
 
+// Записывает только изменившиеся поля. Ключи, которых нет в Settings
+// (например, от более новой версии приложения), не трогаются
 pub async fn save_settings(pool: &Pool<Sqlite>, settings: &Settings) -> Result<()> {
+    let mut document = match serde_json::to_value(settings) {
+        Ok(Value::Object(document)) => document,
+        Ok(_) => return Err(Error::Database("Settings must serialize to an object".to_string())),
+        Err(e) => return Err(Error::Database(e.to_string())),
+    };
+    document.insert("version".to_string(), SETTINGS_VERSION.into());
+
     let mut tx = pool
         .begin()
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
 
-    // Очищаем старые настройки
-    sqlx::query("DELETE FROM settings")
+    let stored: HashMap<String, String> = sqlx::query("SELECT key, value FROM settings")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    for (key, value) in document {
+        let value = value.to_string();
+        if stored.get(&key) == Some(&value) {
+            continue;
+        }
+
+        sqlx::query(
+            "INSERT INTO settings (key, value) VALUES (?, ?)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(key)
+        .bind(value)
         .execute(&mut *tx)
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
+    }
 
-    // Сохраняем базовые настройки
-    let settings_to_save = [
-        ("auto_update", settings.auto_update.to_string()),
-        ("notifications", settings.notifications.to_string()),
-        ("check_interval", settings.check_interval.to_string()),
-        ("cache_ttl_minutes", settings.cache_ttl_minutes.to_string()),
-        ("cache_size", settings.cache_size.to_string()),
-        ("log_level", settings.logging.level.clone()),
-        ("log_file_name", settings.logging.file_name.clone()),
-        (
-            "log_rotation",
-            match settings.logging.rotation {
-                LogRotation::Minutely => "minutely",
-                LogRotation::Hourly => "hourly",
-                LogRotation::Daily => "daily",
-                LogRotation::Never => "never",
-            }
-            .to_string(),
-        ),
-    ];
+    tx.commit()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
 
-    for (key, value) in settings_to_save {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind(key)
-            .bind(value)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
-    }
+    Ok(())
+}
 
-    // Сохраняем пути
-    if let Some(path) = &settings.paths.steam {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind("steam_path")
-            .bind(path)
-            .execute(&mut *tx)
+// Миграция 4: строки прежнего формата, где значения хранились текстом под
+// собственными именами, переводятся в JSON-значения полей Settings.
+// Неизвестные ключи и открытый пароль остаются без изменений
+pub(crate) fn convert_legacy_settings(conn: &mut SqliteConnection) -> MigrationFuture<'_> {
+    Box::pin(async move {
+        let rows = sqlx::query("SELECT key, value FROM settings")
+            .fetch_all(&mut *conn)
             .await
             .map_err(|e| Error::Database(e.to_string()))?;
-    }
 
-    if let Some(path) = &settings.paths.epic {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind("epic_path")
-            .bind(path)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
-    }
+        let mut converted = Vec::new();
+        let mut document = Map::new();
+        let mut paths = Map::new();
+        let mut logging = Map::new();
 
-    // Сохраняем настройки логирования
-    if let Some(path) = &settings.logging.custom_path {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind("log_custom_path")
-            .bind(path.to_string_lossy().to_string())
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
-    }
+        for row in rows {
+            let key: String = row.get(0);
+            let value: String = row.get(1);
+            // Текст, который не разбирается как JSON, сохраняем строкой:
+            // при загрузке поле получит значение по умолчанию с предупреждением
+            let json = || serde_json::from_str(&value).unwrap_or_else(|_| Value::String(value.clone()));
 
-    // Сохраняем учетные данные Steam
-    if let Some(username) = &settings.steam_username {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind("steam_username")
-            .bind(username)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
-    }
+            match key.as_str() {
+                "auto_update" | "notifications" | "check_interval" | "update_interval"
+                | "maintenance_windows" | "pause_outside_windows" | "cache_ttl_minutes"
+                | "cache_size" => {
+                    document.insert(key.clone(), json());
+                }
+                "retry_policy" => {
+                    document.insert("retry".to_string(), json());
+                }
+                "steam_username" | "steam_password_ref" | "custom_steamcmd_path" => {
+                    document.insert(key.clone(), Value::String(value.clone()));
+                }
+                "steam_path" => {
+                    paths.insert("steam".to_string(), Value::String(value.clone()));
+                }
+                "epic_path" => {
+                    paths.insert("epic".to_string(), Value::String(value.clone()));
+                }
+                "log_level" => {
+                    logging.insert("level".to_string(), Value::String(value.clone()));
+                }
+                "log_file_name" => {
+                    logging.insert("file_name".to_string(), Value::String(value.clone()));
+                }
+                "log_custom_path" => {
+                    logging.insert("custom_path".to_string(), Value::String(value.clone()));
+                }
+                "log_rotation" => {
+                    let rotation = match value.as_str() {
+                        "minutely" => "Minutely",
+                        "hourly" => "Hourly",
+                        "never" => "Never",
+                        _ => "Daily",
+                    };
+                    logging.insert("rotation".to_string(), rotation.into());
+                }
+                _ => continue,
+            }
+            converted.push(key);
+        }
 
-    if let Some(password) = &settings.steam_password {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind("steam_password")
-            .bind(password)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
-    }
+        if !paths.is_empty() {
+            document.insert("paths".to_string(), Value::Object(paths));
+        }
+        if !logging.is_empty() {
+            document.insert("logging".to_string(), Value::Object(logging));
+        }
+        document.insert("version".to_string(), SETTINGS_VERSION.into());
+
+        for key in converted {
+            sqlx::query("DELETE FROM settings WHERE key = ?")
+                .bind(key)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| Error::Database(e.to_string()))?;
+        }
 
-    // Сохраняем путь к SteamCMD
-    if let Some(path) = &settings.custom_steamcmd_path {
-        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-            .bind("custom_steamcmd_path")
-            .bind(path.to_string_lossy().to_string())
-            .execute(&mut *tx)
+        for (key, value) in document {
+            sqlx::query(
+                "INSERT INTO settings (key, value) VALUES (?, ?)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            )
+            .bind(key)
+            .bind(value.to_string())
+            .execute(&mut *conn)
             .await
             .map_err(|e| Error::Database(e.to_string()))?;
-    }
+        }
+
+        Ok(())
+    })
+}
+
+// Пароль, сохранённый открытым текстом до появления хранилища секретов
+pub async fn load_legacy_password(pool: &Pool<Sqlite>) -> Result<Option<String>> {
+    let row = sqlx::query("SELECT value FROM settings WHERE key = ?")
+        .bind(LEGACY_PASSWORD_KEY)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(row.map(|row| row.get(0)))
+}
+
+// Заменяет открытый пароль ссылкой на секрет
+pub async fn replace_legacy_password(pool: &Pool<Sqlite>, reference: &str) -> Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    sqlx::query("DELETE FROM settings WHERE key IN ('steam_password', 'steam_password_ref')")
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    sqlx::query("INSERT INTO settings (key, value) VALUES ('steam_password_ref', ?)")
+        .bind(Value::from(reference).to_string())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
 
     tx.commit()
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
 
     Ok(())
-} 
\ No newline at end of file
+}
@@ -1,24 +1,122 @@
 use std::sync::Arc;
 use sqlx::{Pool, Sqlite};
+use tokio::sync::Mutex;
 use crate::error::Result;
-use super::{Settings, db};
+use crate::secrets::SecretStore;
+use crate::{log_error, log_info};
+use crate::logging::register_secret;
+use super::{Settings, SettingChange, SettingsUpdate, db, patch};
 
 #[derive(Clone)]
 pub struct SettingsManager {
     pool: Arc<Pool<Sqlite>>,
+    secrets: SecretStore,
+    // Чтение, изменение и запись настроек не должны перемежаться
+    write_lock: Arc<Mutex<()>>,
 }
 
 impl SettingsManager {
     pub async fn new() -> Result<Self> {
         let pool = db::init_database().await?;
-        Ok(Self { pool: Arc::new(pool) })
+        let secrets = SecretStore::open(&db::data_dir()?);
+        let manager = Self {
+            pool: Arc::new(pool),
+            secrets,
+            write_lock: Arc::new(Mutex::new(())),
+        };
+        manager.migrate_legacy_password().await?;
+        Ok(manager)
+    }
+
+    // Общая база приложения: в ней же хранится очередь обновлений
+    pub fn pool(&self) -> Arc<Pool<Sqlite>> {
+        self.pool.clone()
     }
 
     pub async fn load(&self) -> Result<Settings> {
         db::load_settings(&self.pool).await
     }
 
-    pub async fn save(&self, settings: &Settings) -> Result<()> {
-        db::save_settings(&self.pool, settings).await
+    // Возвращает сохранённые настройки: пароль в них заменён ссылкой
+    pub async fn save(&self, settings: &Settings) -> Result<Settings> {
+        let _guard = self.write_lock.lock().await;
+        let current = self.load().await?;
+        settings.validate(&current)?;
+        self.store(settings.clone()).await
+    }
+
+    // Применяет изменения к сохранённым настройкам одной операцией.
+    // Возвращает новые настройки и изменившиеся поля
+    pub async fn update(&self, updates: &[SettingsUpdate]) -> Result<(Settings, Vec<SettingChange>)> {
+        let _guard = self.write_lock.lock().await;
+        let current = self.load().await?;
+        let updated = patch::apply(&current, updates)?;
+        updated.validate(&current)?;
+
+        let saved = self.store(updated).await?;
+        let changes = patch::diff(&current, &saved);
+        Ok((saved, changes))
+    }
+
+    // Пароль уходит в хранилище секретов, в базу попадает только ссылка
+    async fn store(&self, mut settings: Settings) -> Result<Settings> {
+        let password = settings.steam_password.take().filter(|p| !p.is_empty());
+
+        match (&settings.steam_username, password) {
+            (Some(username), Some(password)) => {
+                register_secret(&password);
+                let reference = self.secrets.store(&password_key(username), &password)?;
+                if let Some(old) = settings.steam_password_ref.replace(reference.clone()) {
+                    if old != reference {
+                        self.secrets.remove(&old)?;
+                    }
+                }
+            }
+            // Без имени пользователя пароль не нужен
+            (None, _) => {
+                if let Some(old) = settings.steam_password_ref.take() {
+                    self.secrets.remove(&old)?;
+                }
+            }
+            (Some(_), None) => {}
+        }
+
+        db::save_settings(&self.pool, &settings).await?;
+        Ok(settings)
+    }
+
+    pub fn steam_password(&self, settings: &Settings) -> Result<Option<String>> {
+        match &settings.steam_password_ref {
+            Some(reference) => self.secrets.resolve(reference),
+            None => Ok(None),
+        }
+    }
+
+    // Настройки с паролем Steam из хранилища секретов: он нужен только SteamCMD
+    pub fn with_password(&self, mut settings: Settings) -> Settings {
+        settings.steam_password = self.steam_password(&settings).unwrap_or_else(|e| {
+            log_error!("Failed to read Steam password: {}", e);
+            None
+        });
+        settings
+    }
+
+    async fn migrate_legacy_password(&self) -> Result<()> {
+        let password = match db::load_legacy_password(&self.pool).await? {
+            Some(password) => password,
+            None => return Ok(()),
+        };
+
+        let settings = self.load().await?;
+        let username = settings.steam_username.as_deref().unwrap_or("default");
+        let reference = self.secrets.store(&password_key(username), &password)?;
+        db::replace_legacy_password(&self.pool, &reference).await?;
+
+        log_info!("Moved Steam password from settings.db to the secret store");
+        Ok(())
     }
-} 
\ No newline at end of file
+}
+
+fn password_key(username: &str) -> String {
+    format!("steam-password/{}", username)
+}
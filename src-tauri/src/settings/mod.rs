@@ -1,13 +1,28 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use crate::games::retry::RetryPolicy;
 use crate::logging::LogConfig;
+use crate::scheduler::MaintenanceWindow;
 
 mod db;
 pub mod manager;
+mod patch;
+mod validation;
 
-pub use db::{load_settings, save_settings};
+pub use db::{load_settings, save_settings, SETTINGS_VERSION};
+pub(crate) use db::convert_legacy_settings;
+pub use patch::{SettingChange, SettingsPatch};
 
+// Сколько обновлений одной платформы выполняет очередь одновременно. steamcmd
+// работает в одной сессии, поэтому для Steam больше одного не имеет смысла
+pub const MAX_CONCURRENT_STEAM_UPDATES: usize = 1;
+pub const MAX_CONCURRENT_EPIC_UPDATES: usize = 1;
+
+// Хранится в таблице settings: каждое поле верхнего уровня - отдельный ключ
+// с JSON-значением. Отсутствующие поля берутся из Default, поэтому новые
+// поля не ломают старые базы
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Settings {
     pub steam_path: Option<String>,
     pub epic_path: Option<String>,
@@ -15,10 +30,19 @@ pub struct Settings {
     pub update_interval: i64, // в часах
     pub notifications: bool,
     pub check_interval: i64,
+    // Автоматические обновления запускаются только внутри окон; пустой список - без ограничений
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    // Приостанавливать автоматические обновления, когда окно закрывается
+    pub pause_outside_windows: bool,
+    // Повтор обновления после временных ошибок steamcmd
+    pub retry: RetryPolicy,
     pub paths: GamePaths,
-    // Steam авторизация
+    // Steam авторизация. Пароль только принимается от фронтенда и передаётся
+    // в SteamCMD, в базе хранится лишь ссылка на него в хранилище секретов
     pub steam_username: Option<String>,
+    #[serde(skip_serializing)]
     pub steam_password: Option<String>,
+    pub steam_password_ref: Option<String>,
     // Системные настройки
     pub cache_ttl_minutes: i64,
     pub cache_size: usize,
@@ -26,7 +50,8 @@ pub struct Settings {
     pub custom_steamcmd_path: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GamePaths {
     pub steam: Option<String>,
     pub epic: Option<String>,
@@ -41,12 +66,16 @@ impl Default for Settings {
             update_interval: 24,
             notifications: true,
             check_interval: 30,
+            maintenance_windows: Vec::new(),
+            pause_outside_windows: false,
+            retry: RetryPolicy::default(),
             paths: GamePaths {
                 steam: None,
                 epic: None,
             },
             steam_username: None,
             steam_password: None,
+            steam_password_ref: None,
             cache_ttl_minutes: 30,
             cache_size: 1000,
             logging: LogConfig::default(),
@@ -55,6 +84,7 @@ impl Default for Settings {
     }
 }
 
+// Изменение одного поля для update_settings, см. patch::apply
 #[derive(Debug, Deserialize)]
 pub struct SettingsUpdate {
     pub key: String,
@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::error::{Error, FieldError, Result};
+use super::{Settings, SettingsUpdate};
+
+// Пароль не сериализуется, но его можно задать через update_settings
+const WRITE_ONLY_KEYS: &[&str] = &["steam_password"];
+
+// Одно изменение, несколько, применяемых вместе, или часть объекта
+// настроек ({ autoUpdate: true, steamPath: "..." }), как Partial<Settings>
+// во фронтенде. Порядок вариантов важен: объект { key, value } - это One
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SettingsPatch {
+    One(SettingsUpdate),
+    Many(Vec<SettingsUpdate>),
+    Partial(Map<String, Value>),
+}
+
+impl SettingsPatch {
+    pub fn into_updates(self) -> Vec<SettingsUpdate> {
+        match self {
+            SettingsPatch::One(update) => vec![update],
+            SettingsPatch::Many(updates) => updates,
+            // Каждое поле объекта - отдельное изменение; camelCase
+            // приводится к snake_case в apply, как и для ключей
+            SettingsPatch::Partial(fields) => fields
+                .into_iter()
+                .map(|(key, value)| SettingsUpdate { key, value })
+                .collect(),
+        }
+    }
+}
+
+// Полезная нагрузка события settings-changed
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+// Ключ - JSON pointer (/retry/jitter) или путь через точку (retry.jitter).
+// Имена в camelCase, как их шлёт фронтенд, приводятся к snake_case.
+// Применяется всё или ничего: при любой ошибке возвращаются ошибки всех полей
+pub fn apply(settings: &Settings, updates: &[SettingsUpdate]) -> Result<Settings> {
+    let mut document = serde_json::to_value(settings).map_err(|e| Error::ParseError(e.to_string()))?;
+    let mut errors = Vec::new();
+
+    for update in updates {
+        let path = parse_key(&update.key);
+        let field = path.join(".");
+
+        let mut candidate = document.clone();
+        if let Err(message) = set(&mut candidate, &path, update.value.clone()) {
+            errors.push(FieldError::new(field, message));
+            continue;
+        }
+        match serde_json::from_value::<Settings>(candidate.clone()) {
+            Ok(_) => document = candidate,
+            Err(e) => errors.push(FieldError::new(field, format!("Неверное значение: {}", e))),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::Validation(errors));
+    }
+    serde_json::from_value(document).map_err(|e| Error::ParseError(e.to_string()))
+}
+
+// Изменившиеся поля верхнего уровня, как они хранятся в базе
+pub fn diff(old: &Settings, new: &Settings) -> Vec<SettingChange> {
+    let (old, new) = match (to_map(old), to_map(new)) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return Vec::new(),
+    };
+
+    new.into_iter()
+        .filter_map(|(key, new)| {
+            let old = old.get(&key).cloned().unwrap_or(Value::Null);
+            (old != new).then_some(SettingChange { key, old, new })
+        })
+        .collect()
+}
+
+fn to_map(settings: &Settings) -> Option<Map<String, Value>> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => Some(map),
+        _ => None,
+    }
+}
+
+fn parse_key(key: &str) -> Vec<String> {
+    let segments: Vec<String> = match key.strip_prefix('/') {
+        Some(pointer) => pointer
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+        None => key.split('.').map(str::to_string).collect(),
+    };
+    segments.iter().map(|segment| to_snake_case(segment)).collect()
+}
+
+fn to_snake_case(segment: &str) -> String {
+    let mut result = String::with_capacity(segment.len() + 4);
+    for c in segment.chars() {
+        if c.is_ascii_uppercase() {
+            if !result.is_empty() {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+// Задаёт только существующие поля, чтобы опечатка в ключе не терялась молча.
+// В массиве индекс "-" добавляет элемент в конец
+fn set(document: &mut Value, path: &[String], value: Value) -> std::result::Result<(), String> {
+    let (last, parents) = match path.split_last() {
+        Some((last, parents)) if !last.is_empty() => (last, parents),
+        _ => return Err("Пустой ключ настройки".to_string()),
+    };
+
+    let mut target = document;
+    for segment in parents {
+        target = match target {
+            Value::Object(map) => map.get_mut(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| "Неизвестная настройка".to_string())?;
+    }
+
+    match target {
+        Value::Object(map) if map.contains_key(last) || (parents.is_empty() && WRITE_ONLY_KEYS.contains(&last.as_str())) => {
+            map.insert(last.clone(), value);
+            Ok(())
+        }
+        Value::Array(items) if last == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        Value::Array(items) => {
+            let slot = last
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get_mut(i))
+                .ok_or_else(|| "Индекс за пределами списка".to_string())?;
+            *slot = value;
+            Ok(())
+        }
+        _ => Err("Неизвестная настройка".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    fn patch(value: Value) -> Vec<SettingsUpdate> {
+        serde_json::from_value::<SettingsPatch>(value).unwrap().into_updates()
+    }
+
+    fn changed_keys(old: &Settings, new: &Settings) -> Vec<String> {
+        let mut keys: Vec<_> = diff(old, new).into_iter().map(|change| change.key).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn applies_single_update() {
+        let old = Settings::default();
+        let new = apply(&old, &patch(json!({ "key": "autoUpdate", "value": true }))).unwrap();
+
+        assert!(new.auto_update);
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].key, "auto_update");
+        assert_eq!(changes[0].old, json!(false));
+        assert_eq!(changes[0].new, json!(true));
+    }
+
+    #[test]
+    fn applies_update_list() {
+        let old = Settings::default();
+        let updates = patch(json!([
+            { "key": "/retry/maxAttempts", "value": 5 },
+            { "key": "paths.steam", "value": "/games/steam" },
+            { "key": "checkInterval", "value": 60 },
+        ]));
+        let new = apply(&old, &updates).unwrap();
+
+        assert_eq!(new.retry.max_attempts, 5);
+        assert_eq!(new.paths.steam.as_deref(), Some("/games/steam"));
+        assert_eq!(new.check_interval, 60);
+        assert_eq!(changed_keys(&old, &new), ["check_interval", "paths", "retry"]);
+    }
+
+    #[test]
+    fn applies_partial_settings() {
+        let old = Settings::default();
+        let updates = patch(json!({ "autoUpdate": true, "steamPath": "/opt/steam", "updateInterval": 12 }));
+        let new = apply(&old, &updates).unwrap();
+
+        assert!(new.auto_update);
+        assert_eq!(new.steam_path.as_deref(), Some("/opt/steam"));
+        assert_eq!(new.update_interval, 12);
+        assert_eq!(changed_keys(&old, &new), ["auto_update", "steam_path", "update_interval"]);
+    }
+
+    #[test]
+    fn empty_partial_changes_nothing() {
+        let old = Settings::default();
+        let new = apply(&old, &patch(json!({}))).unwrap();
+
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn rejects_whole_patch_with_all_field_errors() {
+        let old = Settings::default();
+        let updates = patch(json!({ "autoUpdate": "yes", "unknownField": 1, "notifications": false }));
+
+        match apply(&old, &updates) {
+            Err(Error::Validation(errors)) => {
+                let mut fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+                fields.sort();
+                assert_eq!(fields, ["auto_update", "unknown_field"]);
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn appends_to_array_and_sets_write_only_password() {
+        let old = Settings::default();
+        let updates = patch(json!([
+            { "key": "/maintenanceWindows/-", "value": { "kind": "cron", "expression": "0 0 3 * * *", "duration_minutes": 60 } },
+            { "key": "steamPassword", "value": "secret" },
+        ]));
+        let new = apply(&old, &updates).unwrap();
+
+        assert_eq!(new.maintenance_windows.len(), 1);
+        assert_eq!(new.steam_password.as_deref(), Some("secret"));
+        // Пароль не сериализуется и не попадает в событие settings-changed
+        assert_eq!(changed_keys(&old, &new), ["maintenance_windows"]);
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::Path;
+use crate::error::{Error, FieldError, Result};
+use crate::scheduler::MaintenanceWindow;
+use super::Settings;
+
+const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error"];
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+const MAX_RETRY_DELAY_SECS: u64 = 60 * 60;
+
+impl Settings {
+    // Проверяет все поля сразу, чтобы фронтенд мог подсветить каждое неверное.
+    // Существование папок и файлов проверяется только у полей, изменившихся
+    // относительно previous: библиотека на отключённом диске не должна
+    // мешать сохранить другие настройки
+    pub fn validate(&self, previous: &Settings) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if !(1..=24 * 60).contains(&self.check_interval) {
+            errors.push(FieldError::new("check_interval", "Интервал проверки должен быть от 1 до 1440 минут"));
+        }
+        if !(0..=24 * 30).contains(&self.update_interval) {
+            errors.push(FieldError::new("update_interval", "Интервал обновлений должен быть от 0 до 720 часов"));
+        }
+        if self.cache_ttl_minutes < 1 {
+            errors.push(FieldError::new("cache_ttl_minutes", "Время жизни кэша должно быть не меньше минуты"));
+        }
+        if self.cache_size == 0 {
+            errors.push(FieldError::new("cache_size", "Размер кэша должен быть больше нуля"));
+        }
+
+        for (field, path, old) in [
+            ("steam_path", &self.steam_path, &previous.steam_path),
+            ("epic_path", &self.epic_path, &previous.epic_path),
+            ("paths.steam", &self.paths.steam, &previous.paths.steam),
+            ("paths.epic", &self.paths.epic, &previous.paths.epic),
+        ] {
+            if let Some(path) = path.as_ref().filter(|_| path != old) {
+                check_dir(&mut errors, field, Path::new(path));
+            }
+        }
+        if let Some(path) = &self.custom_steamcmd_path {
+            if self.custom_steamcmd_path != previous.custom_steamcmd_path && !path.is_file() {
+                errors.push(FieldError::new("custom_steamcmd_path", "Файл SteamCMD не найден"));
+            }
+        }
+
+        if !LOG_LEVELS.contains(&self.logging.level.as_str()) {
+            errors.push(FieldError::new(
+                "logging.level",
+                format!("Уровень логирования должен быть одним из: {}", LOG_LEVELS.join(", ")),
+            ));
+        }
+        if self.logging.file_name.trim().is_empty() {
+            errors.push(FieldError::new("logging.file_name", "Имя файла журнала не может быть пустым"));
+        }
+        if let Some(path) = &self.logging.custom_path {
+            if self.logging.custom_path != previous.logging.custom_path {
+                check_dir(&mut errors, "logging.custom_path", path);
+            }
+        }
+
+        for (index, window) in self.maintenance_windows.iter().enumerate() {
+            if let Some(message) = window_error(window) {
+                errors.push(FieldError::new(format!("maintenance_windows.{}", index), message));
+            }
+        }
+
+        let retry = &self.retry;
+        if !(1..=MAX_RETRY_ATTEMPTS).contains(&retry.max_attempts) {
+            errors.push(FieldError::new(
+                "retry.max_attempts",
+                format!("Число попыток должно быть от 1 до {}", MAX_RETRY_ATTEMPTS),
+            ));
+        }
+        if retry.max_delay_secs > MAX_RETRY_DELAY_SECS {
+            errors.push(FieldError::new(
+                "retry.max_delay_secs",
+                format!("Максимальная задержка не может превышать {} секунд", MAX_RETRY_DELAY_SECS),
+            ));
+        }
+        if retry.initial_delay_secs > retry.max_delay_secs {
+            errors.push(FieldError::new(
+                "retry.initial_delay_secs",
+                "Начальная задержка не может превышать максимальную",
+            ));
+        }
+        if retry.multiplier.is_nan() || retry.multiplier < 1.0 {
+            errors.push(FieldError::new("retry.multiplier", "Множитель задержки должен быть не меньше 1"));
+        }
+        if !(0.0..=1.0).contains(&retry.jitter) {
+            errors.push(FieldError::new("retry.jitter", "Разброс задержки должен быть от 0 до 1"));
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Validation(errors)),
+        }
+    }
+}
+
+fn check_dir(errors: &mut Vec<FieldError>, field: &str, path: &Path) {
+    if !path.is_dir() {
+        errors.push(FieldError::new(field, format!("Папка {} не найдена", path.display())));
+    }
+}
+
+fn window_error(window: &MaintenanceWindow) -> Option<String> {
+    match window {
+        MaintenanceWindow::Weekly { days, .. } if days.is_empty() => {
+            Some("Не выбраны дни недели".to_string())
+        }
+        MaintenanceWindow::Cron { duration_minutes: 0, .. } => {
+            Some("Длительность окна должна быть больше нуля".to_string())
+        }
+        _ => match window.validate() {
+            Ok(()) => None,
+            Err(Error::ConfigError(message)) => Some(message),
+            Err(e) => Some(e.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::GamePaths;
+    use super::*;
+
+    const MISSING: &str = "/nonexistent/updateio/library";
+
+    fn fields(result: Result<()>) -> Vec<String> {
+        match result {
+            Ok(()) => Vec::new(),
+            Err(Error::Validation(errors)) => errors.into_iter().map(|error| error.field).collect(),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn unchanged_missing_path_does_not_block_save() {
+        let previous = Settings {
+            paths: GamePaths {
+                steam: Some(MISSING.to_string()),
+                epic: None,
+            },
+            ..Settings::default()
+        };
+        let settings = Settings {
+            auto_update: true,
+            ..previous.clone()
+        };
+
+        assert!(fields(settings.validate(&previous)).is_empty());
+    }
+
+    #[test]
+    fn changed_missing_path_is_rejected() {
+        let previous = Settings::default();
+        let mut settings = previous.clone();
+        settings.paths.steam = Some(MISSING.to_string());
+        settings.custom_steamcmd_path = Some(MISSING.into());
+
+        assert_eq!(fields(settings.validate(&previous)), ["paths.steam", "custom_steamcmd_path"]);
+    }
+
+    #[test]
+    fn changed_existing_path_is_accepted() {
+        let previous = Settings::default();
+        let mut settings = previous.clone();
+        settings.steam_path = Some(std::env::temp_dir().to_string_lossy().into_owned());
+
+        assert!(fields(settings.validate(&previous)).is_empty());
+    }
+
+    #[test]
+    fn all_invalid_fields_are_reported() {
+        let previous = Settings::default();
+        let mut settings = previous.clone();
+        settings.check_interval = 0;
+        settings.retry.max_delay_secs = u64::MAX;
+        settings.retry.jitter = 2.0;
+
+        assert_eq!(
+            fields(settings.validate(&previous)),
+            ["check_interval", "retry.max_delay_secs", "retry.jitter"]
+        );
+    }
+}
@@ -0,0 +1,632 @@
+use super::guard::{SteamGuardBroker, SteamGuardKind, SteamGuardRequest};
+use super::parser::failure_error;
+use super::session::{CancelHandle, OutputLine, Prompt, SteamCmdSession};
+use crate::error::{Error, Result, SteamCmdFailure};
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+const QUEUE_SIZE: usize = 64;
+const IDLE_SHUTDOWN: Duration = Duration::from_secs(300);
+// Первый запуск steamcmd включает самообновление
+const STARTUP_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_GUARD_ATTEMPTS: u32 = 3;
+
+const DISCONNECT_MARKERS: &[&str] = &[
+    "Connection to Steam servers lost",
+    "No connection",
+    "Not logged on",
+];
+
+pub type LineCallback = Box<dyn Fn(&OutputLine) + Send + Sync>;
+
+// Набор команд, выполняемых подряд в одной сессии
+pub struct SteamCmdRequest {
+    commands: Vec<String>,
+    install_dir: Option<PathBuf>,
+    timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    on_line: Option<LineCallback>,
+    cancel: Option<CancelHandle>,
+}
+
+impl SteamCmdRequest {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            commands: vec![command.into()],
+            install_dir: None,
+            timeout: None,
+            idle_timeout: None,
+            on_line: None,
+            cancel: None,
+        }
+    }
+
+    pub fn then(mut self, command: impl Into<String>) -> Self {
+        self.commands.push(command.into());
+        self
+    }
+
+    pub fn with_install_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.install_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn on_line(mut self, callback: impl Fn(&OutputLine) + Send + Sync + 'static) -> Self {
+        self.on_line = Some(Box::new(callback));
+        self
+    }
+
+    // Отмена завершает steamcmd; следующий запрос запустит новую сессию
+    pub fn with_cancel(mut self, cancel: CancelHandle) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+struct Job {
+    request: SteamCmdRequest,
+    reply: oneshot::Sender<Result<Vec<OutputLine>>>,
+}
+
+// Без имени пользователя используется анонимный вход
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SteamCredentials {
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+struct ClientConfig {
+    steamcmd_path: PathBuf,
+    // Меняются при сохранении настроек; сессия с прежними данными перезапускается
+    credentials: Mutex<SteamCredentials>,
+    guard: Arc<SteamGuardBroker>,
+}
+
+impl SteamCredentials {
+    // Пароль в команду не попадает: steamcmd запросит его через stdin, если
+    // у него нет сохранённого токена входа
+    fn login_command(&self) -> String {
+        match &self.username {
+            Some(username) => format!("login {}", username),
+            None => "login anonymous".to_string(),
+        }
+    }
+}
+
+// Очередь к одному долгоживущему steamcmd. Процесс запускается при первом
+// запросе и завершается после простоя
+#[derive(Clone)]
+pub struct SteamCmdClient {
+    config: Arc<ClientConfig>,
+    sender: Arc<Mutex<Option<mpsc::Sender<Job>>>>,
+}
+
+impl SteamCmdClient {
+    pub fn new(
+        steamcmd_path: PathBuf,
+        credentials: SteamCredentials,
+        guard: Arc<SteamGuardBroker>,
+    ) -> Self {
+        Self {
+            config: Arc::new(ClientConfig {
+                steamcmd_path,
+                credentials: Mutex::new(credentials),
+                guard,
+            }),
+            sender: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn steamcmd_path(&self) -> &Path {
+        &self.config.steamcmd_path
+    }
+
+    // Новые данные входа применяются со следующего запроса
+    pub fn set_credentials(&self, credentials: SteamCredentials) {
+        *self.config.credentials.lock() = credentials;
+    }
+
+    pub async fn execute(&self, request: SteamCmdRequest) -> Result<Vec<OutputLine>> {
+        let (reply, response) = oneshot::channel();
+        let mut job = Job { request, reply };
+
+        // Обработчик мог только что завершиться по простою, тогда запускаем новый
+        for _ in 0..2 {
+            match self.sender().send(job).await {
+                Ok(()) => {
+                    return response.await.map_err(|_| {
+                        Error::ProcessError("steamcmd worker stopped unexpectedly".to_string())
+                    })?;
+                }
+                Err(mpsc::error::SendError(returned)) => {
+                    job = returned;
+                    self.sender.lock().take();
+                }
+            }
+        }
+
+        Err(Error::ProcessError("Failed to start steamcmd worker".to_string()))
+    }
+
+    fn sender(&self) -> mpsc::Sender<Job> {
+        let mut sender = self.sender.lock();
+        if let Some(sender) = sender.as_ref().filter(|s| !s.is_closed()) {
+            return sender.clone();
+        }
+
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(Worker::new(self.config.clone()).run(rx));
+        *sender = Some(tx.clone());
+        tx
+    }
+}
+
+enum Outcome {
+    Done(Vec<OutputLine>),
+    // Процесс завершился или потерял соединение: запрос можно повторить
+    Disconnected(String),
+    Failed(Error),
+}
+
+struct Worker {
+    config: Arc<ClientConfig>,
+    session: Option<SteamCmdSession>,
+    install_dir: Option<PathBuf>,
+    // С какими данными выполнен вход в текущей сессии
+    credentials: SteamCredentials,
+}
+
+impl Worker {
+    fn new(config: Arc<ClientConfig>) -> Self {
+        Self {
+            config,
+            session: None,
+            install_dir: None,
+            credentials: SteamCredentials::default(),
+        }
+    }
+
+    async fn run(mut self, mut jobs: mpsc::Receiver<Job>) {
+        loop {
+            match tokio::time::timeout(IDLE_SHUTDOWN, jobs.recv()).await {
+                Ok(Some(job)) => self.handle(job).await,
+                Ok(None) => break,
+                Err(_) => {
+                    // Новые запросы уже уйдут в следующий обработчик
+                    jobs.close();
+                    while let Ok(job) = jobs.try_recv() {
+                        self.handle(job).await;
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.shutdown().await;
+    }
+
+    async fn handle(&mut self, job: Job) {
+        let Job { request, reply } = job;
+
+        let mut result = self.execute(&request).await;
+        if let Outcome::Disconnected(reason) = &result {
+            warn!("steamcmd session lost ({}), reconnecting", reason);
+            result = self.execute(&request).await;
+        }
+
+        let result = match result {
+            Outcome::Done(lines) => Ok(lines),
+            Outcome::Disconnected(reason) => Err(failure_error(&reason, |message| Error::SteamCmd {
+                kind: SteamCmdFailure::ConnectionLost,
+                message,
+            })),
+            Outcome::Failed(e) => Err(e),
+        };
+        let _ = reply.send(result);
+    }
+
+    async fn execute(&mut self, request: &SteamCmdRequest) -> Outcome {
+        // Запрос могли отменить, пока он ждал в очереди
+        if request.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+            return Outcome::Failed(Error::Cancelled);
+        }
+
+        if let Err(e) = self.prepare(request.install_dir.as_deref()).await {
+            return e;
+        }
+
+        if let (Some(session), Some(cancel)) = (self.session.as_mut(), &request.cancel) {
+            session.set_cancel_handle(cancel.clone());
+        }
+
+        let mut output = Vec::new();
+        let mut outcome = None;
+        for command in &request.commands {
+            match self
+                .command(command, request.timeout, request.idle_timeout, request.on_line.as_ref())
+                .await
+            {
+                Outcome::Done(lines) => output.extend(lines),
+                other => {
+                    outcome = Some(other);
+                    break;
+                }
+            }
+        }
+
+        // Отмена этого запроса не должна затронуть следующие
+        if let (Some(session), Some(_)) = (self.session.as_mut(), &request.cancel) {
+            session.set_cancel_handle(CancelHandle::default());
+        }
+
+        outcome.unwrap_or(Outcome::Done(output))
+    }
+
+    // Запускает steamcmd и выполняет вход; force_install_dir допустим только до входа
+    async fn prepare(&mut self, install_dir: Option<&Path>) -> std::result::Result<(), Outcome> {
+        let credentials = self.config.credentials.lock().clone();
+        if self.session.is_some() && credentials != self.credentials {
+            info!("Steam credentials changed, restarting steamcmd session");
+            self.shutdown().await;
+        }
+        self.credentials = credentials;
+
+        let switch_dir = install_dir
+            .filter(|dir| self.session.is_some() && self.install_dir.as_deref() != Some(*dir));
+
+        if let Some(dir) = switch_dir {
+            debug!("Switching steamcmd install dir to {:?}", dir);
+            expect_done(self.command("logout", Some(LOGIN_TIMEOUT), None, None).await)?;
+            expect_done(
+                self.command(&force_install_dir(dir), Some(LOGIN_TIMEOUT), None, None)
+                    .await,
+            )?;
+            self.install_dir = Some(dir.to_path_buf());
+            return self.login().await;
+        }
+
+        if self.session.is_some() {
+            return Ok(());
+        }
+
+        info!("Starting steamcmd session");
+        let mut session = SteamCmdSession::spawn_interactive(Command::new(&self.config.steamcmd_path))
+            .map_err(Outcome::Failed)?;
+
+        // Ждём первое приглашение
+        session.set_idle_timeout(Some(STARTUP_IDLE_TIMEOUT));
+        loop {
+            match session.next_line().await {
+                Ok(Some(line)) if line.is_prompt() => break,
+                Ok(Some(line)) => debug!("steamcmd: {}", line.text),
+                Ok(None) => {
+                    return Err(Outcome::Failed(Error::ProcessError(
+                        "steamcmd exited during startup".to_string(),
+                    )))
+                }
+                Err(e) => return Err(Outcome::Failed(e)),
+            }
+        }
+        self.session = Some(session);
+        self.install_dir = None;
+
+        if let Some(dir) = install_dir {
+            expect_done(
+                self.command(&force_install_dir(dir), Some(LOGIN_TIMEOUT), None, None)
+                    .await,
+            )?;
+            self.install_dir = Some(dir.to_path_buf());
+        }
+
+        self.login().await
+    }
+
+    async fn login(&mut self) -> std::result::Result<(), Outcome> {
+        let command = self.credentials.login_command();
+        let lines = expect_done(self.command(&command, Some(LOGIN_TIMEOUT), None, None).await)?;
+
+        // После неверного кода Steam Guard steamcmd спрашивает его повторно,
+        // поэтому FAILED в выводе не означает неудачу, если вход в итоге прошёл
+        let logged_in = lines
+            .iter()
+            .any(|line| line.text.contains("Waiting for user info...OK"));
+        let failure = lines
+            .iter()
+            .find(|line| line.text.contains("FAILED") || line.text.contains("ERROR"));
+
+        if let (false, Some(failure)) = (logged_in, failure) {
+            self.session = None;
+            return Err(Outcome::Failed(failure_error(&failure.text, Error::SteamError)));
+        }
+
+        info!("steamcmd logged in");
+        Ok(())
+    }
+
+    // Отправляет команду и читает вывод до следующего приглашения
+    async fn command(
+        &mut self,
+        command: &str,
+        timeout: Option<Duration>,
+        idle_timeout: Option<Duration>,
+        on_line: Option<&LineCallback>,
+    ) -> Outcome {
+        let session = match self.session.as_mut() {
+            Some(session) => session,
+            None => return Outcome::Disconnected("steamcmd session is not running".to_string()),
+        };
+
+        session.set_timeout(timeout);
+        session.set_idle_timeout(idle_timeout);
+        if let Err(e) = session.send_command(command).await {
+            self.session = None;
+            return Outcome::Disconnected(e.to_string());
+        }
+
+        let mut lines = Vec::new();
+        let mut disconnected = None;
+        let mut guard_attempts = 0;
+        loop {
+            match session.next_line().await {
+                Ok(Some(line)) if line.is_prompt() => break,
+                Ok(Some(line)) if line.prompt().is_some() => {
+                    // Запрос пароля не считается попыткой ввода кода
+                    if line.prompt() != Some(Prompt::Password) {
+                        guard_attempts += 1;
+                    }
+                    let answer = answer_prompt(&self.config.guard, &self.credentials, line.prompt(), guard_attempts).await;
+                    let sent = match answer {
+                        Ok(answer) => session.send_command(&answer).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = sent {
+                        self.session = None;
+                        return Outcome::Failed(e);
+                    }
+                    // Ожидание кода не должно съедать время самой команды
+                    session.set_timeout(timeout);
+                }
+                Ok(Some(line)) => {
+                    if disconnected.is_none() && is_disconnect(&line.text) {
+                        disconnected = Some(line.text.clone());
+                    }
+                    if let Some(callback) = on_line {
+                        callback(&line);
+                    }
+                    lines.push(line);
+                }
+                Ok(None) => {
+                    self.session = None;
+                    return Outcome::Disconnected("steamcmd exited unexpectedly".to_string());
+                }
+                Err(e) => {
+                    self.session = None;
+                    return Outcome::Failed(e);
+                }
+            }
+        }
+
+        match disconnected {
+            Some(reason) => {
+                self.session = None;
+                Outcome::Disconnected(reason)
+            }
+            None => Outcome::Done(lines),
+        }
+    }
+
+    async fn shutdown(&mut self) {
+        if let Some(mut session) = self.session.take() {
+            info!("Stopping steamcmd session");
+            session.set_timeout(Some(LOGIN_TIMEOUT));
+            let _ = session.send_command("quit").await;
+            if let Err(e) = session.wait().await {
+                warn!("steamcmd did not exit cleanly: {}", e);
+            }
+        }
+    }
+}
+
+// Ответ на запрос кода Steam Guard или пароля во время входа
+async fn answer_prompt(
+    guard: &SteamGuardBroker,
+    credentials: &SteamCredentials,
+    prompt: Option<Prompt>,
+    attempt: u32,
+) -> Result<String> {
+    let username = credentials.username.clone().unwrap_or_default();
+
+    let kind = match prompt {
+        Some(Prompt::SteamGuard) => SteamGuardKind::Email,
+        Some(Prompt::TwoFactor) => SteamGuardKind::Mobile,
+        Some(Prompt::Password) => {
+            return credentials.password.clone().ok_or_else(|| Error::SteamCmd {
+                kind: SteamCmdFailure::InvalidPassword,
+                message: format!("steamcmd asked for the password of {}", username),
+            });
+        }
+        _ => return Err(Error::SteamError("Unexpected steamcmd prompt".to_string())),
+    };
+
+    if attempt > MAX_GUARD_ATTEMPTS {
+        return Err(Error::SteamCmd {
+            kind: SteamCmdFailure::TwoFactorMismatch,
+            message: "Too many Steam Guard attempts".to_string(),
+        });
+    }
+
+    info!("Steam Guard code required for {}", username);
+    guard
+        .request_code(SteamGuardRequest {
+            kind,
+            username,
+            attempt,
+        })
+        .await
+}
+
+fn expect_done(outcome: Outcome) -> std::result::Result<Vec<OutputLine>, Outcome> {
+    match outcome {
+        Outcome::Done(lines) => Ok(lines),
+        other => Err(other),
+    }
+}
+
+fn force_install_dir(dir: &Path) -> String {
+    format!("force_install_dir \"{}\"", dir.display())
+}
+
+fn is_disconnect(text: &str) -> bool {
+    DISCONNECT_MARKERS.iter().any(|marker| text.contains(marker))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    const FAKE_STEAMCMD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/steamcmd/fake_steamcmd.sh");
+    const PASSWORD: &str = "secret";
+    const VALID_CODE: &str = "12345";
+    const WRONG_CODE: &str = "00000";
+
+    // Клиент с фальшивым steamcmd. Коды вводятся по очереди из codes, как
+    // пользователем в ответ на событие steam-guard-required; полученные
+    // запросы сохраняются для проверки
+    fn client(
+        username: &str,
+        password: Option<&str>,
+        codes: Vec<&'static str>,
+    ) -> (SteamCmdClient, Arc<SteamGuardBroker>, Arc<Mutex<Vec<SteamGuardRequest>>>) {
+        let guard = Arc::new(SteamGuardBroker::default());
+        let (tx, mut requests) = mpsc::unbounded_channel();
+        guard.set_notifier(move |request| {
+            let _ = tx.send(request);
+        });
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (broker, log) = (guard.clone(), received.clone());
+        tokio::spawn(async move {
+            let mut codes = codes.into_iter().cycle();
+            while let Some(request) = requests.recv().await {
+                log.lock().push(request);
+                broker.submit_code(codes.next().unwrap()).unwrap();
+            }
+        });
+
+        let credentials = SteamCredentials {
+            username: Some(username.to_string()),
+            password: password.map(str::to_string),
+        };
+        let client = SteamCmdClient::new(PathBuf::from(FAKE_STEAMCMD), credentials, guard.clone());
+        (client, guard, received)
+    }
+
+    fn request() -> SteamCmdRequest {
+        SteamCmdRequest::new("app_status 570").with_timeout(Duration::from_secs(10))
+    }
+
+    fn attempts(received: &Mutex<Vec<SteamGuardRequest>>) -> Vec<(SteamGuardKind, u32)> {
+        received.lock().iter().map(|request| (request.kind, request.attempt)).collect()
+    }
+
+    #[tokio::test]
+    async fn submitted_code_unblocks_login() {
+        let (client, guard, received) = client("user", Some(PASSWORD), vec![VALID_CODE]);
+
+        let lines = client.execute(request()).await.unwrap();
+        assert!(lines.iter().any(|line| line.text == "app_status 570 done"));
+        assert!(!guard.is_waiting());
+
+        // Запрос пароля не считается попыткой ввода кода
+        assert_eq!(attempts(&received), [(SteamGuardKind::Email, 1)]);
+        assert_eq!(received.lock()[0].username, "user");
+    }
+
+    #[tokio::test]
+    async fn wrong_code_is_requested_again() {
+        let (client, _guard, received) = client("mobile", Some(PASSWORD), vec![WRONG_CODE, VALID_CODE]);
+
+        client.execute(request()).await.unwrap();
+
+        assert_eq!(
+            attempts(&received),
+            [(SteamGuardKind::Mobile, 1), (SteamGuardKind::Mobile, 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn guard_attempts_are_limited() {
+        let (client, _guard, received) = client("user", Some(PASSWORD), vec![WRONG_CODE]);
+
+        let error = client.execute(request()).await.unwrap_err();
+        assert_eq!(error.kind(), Some(SteamCmdFailure::TwoFactorMismatch));
+
+        let expected: Vec<_> = (1..=MAX_GUARD_ATTEMPTS).map(|attempt| (SteamGuardKind::Email, attempt)).collect();
+        assert_eq!(attempts(&received), expected);
+    }
+
+    #[tokio::test]
+    async fn changed_credentials_restart_session() {
+        let (client, _guard, received) = client("user", Some(PASSWORD), vec![VALID_CODE]);
+        client.execute(request()).await.unwrap();
+
+        // Те же данные - сессия и вход сохраняются
+        client.set_credentials(SteamCredentials {
+            username: Some("user".to_string()),
+            password: Some(PASSWORD.to_string()),
+        });
+        client.execute(request()).await.unwrap();
+        assert_eq!(attempts(&received), [(SteamGuardKind::Email, 1)]);
+
+        client.set_credentials(SteamCredentials {
+            username: Some("mobile".to_string()),
+            password: Some(PASSWORD.to_string()),
+        });
+        client.execute(request()).await.unwrap();
+
+        assert_eq!(
+            attempts(&received),
+            [(SteamGuardKind::Email, 1), (SteamGuardKind::Mobile, 1)]
+        );
+        assert_eq!(received.lock()[1].username, "mobile");
+    }
+
+    #[tokio::test]
+    async fn missing_password_fails_login() {
+        let (client, _guard, received) = client("user", None, vec![VALID_CODE]);
+
+        let error = client.execute(request()).await.unwrap_err();
+        assert_eq!(error.kind(), Some(SteamCmdFailure::InvalidPassword));
+        assert!(received.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn guard_request_without_listener_fails() {
+        let guard = SteamGuardBroker::default();
+        let request = SteamGuardRequest {
+            kind: SteamGuardKind::Email,
+            username: "user".to_string(),
+            attempt: 1,
+        };
+
+        assert!(guard.request_code(request).await.is_err());
+        assert!(!guard.is_waiting());
+        assert!(guard.submit_code(VALID_CODE).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::error::{Error, Result};
+use crate::logging::register_secret;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+// Сколько ждём ввода кода пользователем
+const CODE_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SteamGuardKind {
+    // Код из письма
+    Email,
+    // Код из мобильного аутентификатора
+    Mobile,
+}
+
+// Полезная нагрузка события steam-guard-required
+#[derive(Debug, Clone, Serialize)]
+pub struct SteamGuardRequest {
+    pub kind: SteamGuardKind,
+    pub username: String,
+    pub attempt: u32,
+}
+
+type Notifier = Box<dyn Fn(SteamGuardRequest) + Send + Sync>;
+
+// Передаёт запрос кода во фронтенд и ответ обратно в сессию steamcmd
+#[derive(Default)]
+pub struct SteamGuardBroker {
+    notifier: Mutex<Option<Notifier>>,
+    pending: Mutex<Option<oneshot::Sender<String>>>,
+}
+
+impl SteamGuardBroker {
+    pub fn set_notifier(&self, notifier: impl Fn(SteamGuardRequest) + Send + Sync + 'static) {
+        *self.notifier.lock() = Some(Box::new(notifier));
+    }
+
+    pub async fn request_code(&self, request: SteamGuardRequest) -> Result<String> {
+        let (tx, rx) = oneshot::channel();
+        // Предыдущий запрос, если он был, больше не ждёт ответа
+        *self.pending.lock() = Some(tx);
+
+        match self.notifier.lock().as_ref() {
+            Some(notify) => notify(request),
+            None => {
+                self.pending.lock().take();
+                return Err(Error::SteamError(
+                    "Steam Guard code required, but nobody can provide it".to_string(),
+                ));
+            }
+        }
+
+        match tokio::time::timeout(CODE_TIMEOUT, rx).await {
+            Ok(Ok(code)) => Ok(code),
+            Ok(Err(_)) => Err(Error::Cancelled),
+            Err(_) => {
+                self.pending.lock().take();
+                Err(Error::SteamError("Steam Guard code was not entered in time".to_string()))
+            }
+        }
+    }
+
+    pub fn submit_code(&self, code: &str) -> Result<()> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(Error::ConfigError("Steam Guard code is empty".to_string()));
+        }
+
+        register_secret(code);
+
+        let sender = self
+            .pending
+            .lock()
+            .take()
+            .ok_or_else(|| Error::SteamError("No Steam Guard code was requested".to_string()))?;
+
+        sender
+            .send(code.to_string())
+            .map_err(|_| Error::SteamError("Steam Guard request has expired".to_string()))
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.pending.lock().is_some()
+    }
+}
@@ -1,28 +1,46 @@
 #[allow(dead_code)]
 mod parser;
+mod client;
+mod guard;
+mod session;
+
+pub use client::{SteamCmdClient, SteamCmdRequest, SteamCredentials};
+pub use guard::{SteamGuardBroker, SteamGuardKind, SteamGuardRequest};
+pub use session::{CancelHandle, OutputLine, Prompt, SessionOutput, SteamCmdSession, Stream};
 
 use self::parser::parse_update_status;
 use crate::cache::{Cache, TimedCacheEntry};
 use crate::error::{Result, Error};
+use crate::logging::register_secret;
+use crate::registry::steam::{AppManifest, SteamInstallState, SteamRegistry};
 use crate::settings::Settings;
-use serde::{Deserialize, Serialize};
-use std::future::Future;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
-use std::process::Command;
-use std::process::Stdio;
+use crate::vdf;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tracing::{error, info};
+use parking_lot::Mutex;
+use std::time::Duration;
+use tracing::{error, info, warn};
 
 const CACHE_SIZE: usize = 1000;
 const CACHE_TTL_MINUTES: i64 = 30;
+const APP_INFO_TIMEOUT: Duration = Duration::from_secs(120);
+// steamcmd молчит во время проверки файлов и самообновления
+const UPDATE_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[cfg(windows)]
+pub const STEAMCMD_BINARY: &str = "steamcmd.exe";
+#[cfg(not(windows))]
+pub const STEAMCMD_BINARY: &str = "steamcmd.sh";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct SteamGame {
     pub app_id: u32,
     pub name: String,
     pub install_dir: PathBuf,
+    pub manifest: AppManifest,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
@@ -31,51 +49,90 @@ pub struct UpdateStatus {
     pub status: String,
     pub state: UpdateState,
     pub error: Option<String>,
+    pub bytes_downloaded: Option<u64>,
+    pub bytes_total: Option<u64>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
 pub enum UpdateState {
+    #[default]
     Unknown,
     Starting,
+    Reconfiguring,
+    Validating,
+    Preallocating,
     Downloading,
     Verifying,
+    Committing,
     Extracting,
     Installing,
     Complete,
+    // Игра запущена, обновление ждёт её закрытия
+    GameRunning,
+    // Процесс остановлен, загруженные данные сохранены для продолжения
+    Paused,
+    Cancelled,
+    // Попытка не удалась, следующая начнётся после задержки
+    Retrying,
     Error,
 }
 
-impl Default for UpdateState {
-    fn default() -> Self {
-        Self::Unknown
-    }
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildComparison {
+    pub app_id: u32,
+    pub branch: String,
+    pub local_build_id: Option<u32>,
+    pub remote_build_id: u32,
+    pub time_updated: Option<DateTime<Utc>>,
+    pub needs_update: bool,
 }
 
 #[derive(Clone)]
 pub struct SteamManager {
-    steamcmd_path: PathBuf,
-    update_cache: Arc<Cache<u32, TimedCacheEntry<bool>>>,
-    settings: Arc<Settings>,
+    client: Option<SteamCmdClient>,
+    guard: Arc<SteamGuardBroker>,
+    update_cache: Arc<Cache<u32, TimedCacheEntry<BuildComparison>>>,
 }
 
 impl SteamManager {
-    pub fn new(settings: Settings) -> Result<Self> {
-        let steamcmd_path = if let Some(custom_path) = settings.custom_steamcmd_path.clone() {
-            if std::fs::metadata(&custom_path).is_ok() {
-                custom_path
-            } else {
-                Self::find_steamcmd()?
-            }
-        } else {
-            Self::find_steamcmd()?
-        };
+    // bundled_steamcmd - steamcmd из ресурсов приложения
+    pub fn new(settings: Settings, bundled_steamcmd: Option<PathBuf>) -> Self {
+        let steamcmd_path = settings
+            .custom_steamcmd_path
+            .clone()
+            .into_iter()
+            .chain(bundled_steamcmd)
+            .find(|path| std::fs::metadata(path).is_ok())
+            .or_else(|| Self::find_steamcmd().ok());
+
+        if steamcmd_path.is_none() {
+            error!("steamcmd not found, Steam updates are unavailable");
+        }
 
-        Ok(Self {
-            steamcmd_path,
+        let credentials = credentials(&settings);
+        let guard = Arc::new(SteamGuardBroker::default());
+
+        Self {
+            client: steamcmd_path.map(|path| SteamCmdClient::new(path, credentials, guard.clone())),
+            guard,
             update_cache: Arc::new(Cache::new(CACHE_SIZE)),
-            settings: Arc::new(settings),
-        })
+        }
+    }
+
+    // После сохранения настроек: steamcmd войдёт с новыми данными при следующем запросе
+    pub fn apply_settings(&self, settings: &Settings) {
+        if let Some(client) = &self.client {
+            client.set_credentials(credentials(settings));
+        }
+    }
+
+    // Вызывается, когда steamcmd ждёт код Steam Guard
+    pub fn on_steam_guard_required(&self, notifier: impl Fn(SteamGuardRequest) + Send + Sync + 'static) {
+        self.guard.set_notifier(notifier);
+    }
+
+    pub fn submit_steam_guard_code(&self, code: &str) -> Result<()> {
+        self.guard.submit_code(code)
     }
 
     fn find_steamcmd() -> Result<PathBuf> {
@@ -89,7 +146,7 @@ impl SteamManager {
             .join("resources")
             .join("bin")
             .join("steamcmd")
-            .join("steamcmd.exe");
+            .join(STEAMCMD_BINARY);
 
         if std::fs::metadata(&resource_path).is_ok() {
             info!("Found steamcmd at: {:?}", resource_path);
@@ -100,120 +157,113 @@ impl SteamManager {
         }
     }
 
-    fn build_steam_command(&self) -> Command {
-        let mut cmd = Command::new(&self.steamcmd_path);
-
-        // Если есть учетные данные Steam, используем их
-        if let Some(username) = &self.settings.steam_username {
-            cmd.arg("+login");
-            cmd.arg(username);
-            if let Some(password) = &self.settings.steam_password {
-                cmd.arg(password);
-            }
-        } else {
-            // Иначе используем анонимный вход
-            cmd.arg("+login");
-            cmd.arg("anonymous");
-        }
-
-        cmd
+    fn client(&self) -> Result<&SteamCmdClient> {
+        self.client.as_ref().ok_or(Error::SteamCmdNotFound)
     }
 
+    // Список берём из манифестов библиотек Steam, а не из steamcmd
     pub fn get_installed_games(&self) -> Result<Vec<SteamGame>> {
-        let mut cmd = self.build_steam_command();
-        let output = cmd
-            .arg("+apps_installed")
-            .arg("+quit")
-            .output()
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
-
-        parser::parse_installed_games(&String::from_utf8_lossy(&output.stdout))
+        let registry = SteamRegistry::new()?;
+        Ok(registry
+            .get_installed_apps()?
+            .into_iter()
+            .map(|app| SteamGame {
+                app_id: app.manifest.app_id,
+                name: app.manifest.name.clone(),
+                install_dir: app.install_path,
+                manifest: app.manifest,
+            })
+            .collect())
     }
 
-    pub fn check_for_updates(&self, app_id: u32) -> Result<bool> {
-        // Сначала проверяем кэш
-        if let Some(entry) = self.update_cache.get(&app_id) {
-            if entry.timestamp > chrono::Utc::now() {
-                info!("Using cached update status for app_id: {}", app_id);
-                return Ok(entry.value);
+    // Состояние по манифесту и кэшу клиента Steam, без запуска steamcmd
+    pub fn get_install_state(&self, app_id: u32) -> Result<SteamInstallState> {
+        let registry = SteamRegistry::new()?;
+        let manifest = registry
+            .find_app_manifest(app_id)?
+            .ok_or_else(|| Error::SteamError(format!("App manifest not found for {}", app_id)))?;
+
+        let remote_build = match registry.get_app_info(app_id) {
+            Ok(info) => info.and_then(|info| info.build_id(manifest.branch())),
+            Err(e) => {
+                warn!("Failed to read Steam app cache for {}: {}", app_id, e);
+                None
             }
-        }
-
-        info!("Checking updates for app_id: {}", app_id);
-        let mut cmd = self.build_steam_command();
-        let output = cmd
-            .args(&["+app_status", &app_id.to_string()])
-            .arg("+quit")
-            .output()
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
+        };
 
-        let needs_update = parser::check_update_needed(&String::from_utf8_lossy(&output.stdout))?;
+        Ok(manifest.install_state().with_remote_build(remote_build))
+    }
 
-        // Кэшируем результат
-        info!("Caching update status for app_id: {}", app_id);
-        self.update_cache.set(app_id, TimedCacheEntry::new(needs_update, CACHE_TTL_MINUTES));
+    pub async fn check_for_updates(&self, app_id: u32) -> Result<bool> {
+        self.compare_builds(app_id).await.map(|comparison| comparison.needs_update)
+    }
 
-        Ok(needs_update)
+    // Сравнивает локальную сборку из ACF с актуальной сборкой ветки в Steam
+    pub async fn compare_builds(&self, app_id: u32) -> Result<BuildComparison> {
+        self.compare_builds_batch(&[app_id])
+            .await?
+            .remove(&app_id)
+            .unwrap_or_else(|| Err(Error::SteamError(format!("No app info for {}", app_id))))
     }
 
-    pub async fn update_game(
-        &self,
-        app_id: u32,
-        progress_callback: impl Fn(UpdateStatus) + Send + 'static,
-    ) -> Result<()> {
-        let mut cmd = self.build_steam_command();
-        let mut child = cmd
-            .args(&["+app_update", &app_id.to_string(), "+quit"])
-            .stdout(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
-
-        let stdout = child.stdout.take().unwrap();
-        let reader = std::io::BufReader::new(stdout);
-        let lines = std::io::BufRead::lines(reader);
-
-        for line in lines {
-            if let Ok(line) = line {
-                if let Some((current, total)) = parser::parse_download_progress(&line) {
-                    let progress = if total > 0 {
-                        (current as f32 / total as f32) * 100.0
-                    } else {
-                        0.0
-                    };
-
-                    progress_callback(UpdateStatus {
-                        progress,
-                        status: line,
-                        state: UpdateState::Downloading,
-                        error: None,
-                    });
+    // То же для нескольких игр одним запуском app_info_print: steamcmd
+    // работает в одной сессии, и запрос на каждую игру занимал бы её надолго.
+    // Ошибка всего запроса возвращается как Err, ошибка одной игры - в её записи
+    pub async fn compare_builds_batch(&self, app_ids: &[u32]) -> Result<HashMap<u32, Result<BuildComparison>>> {
+        let mut results = HashMap::new();
+        let mut pending = Vec::new();
+
+        for &app_id in app_ids {
+            match self.update_cache.get(&app_id) {
+                Some(entry) if entry.timestamp > chrono::Utc::now() => {
+                    info!("Using cached update status for app_id: {}", app_id);
+                    results.insert(app_id, Ok(entry.value));
                 }
+                _ if !pending.contains(&app_id) => pending.push(app_id),
+                _ => {}
             }
         }
+        if pending.is_empty() {
+            return Ok(results);
+        }
+
+        let registry = SteamRegistry::new()?;
 
-        let status = child
-            .wait()
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
-
-        if status.success() {
-            // Инвалидируем кэш после успешного обновления
-            self.update_cache.invalidate(&app_id);
-            progress_callback(UpdateStatus {
-                progress: 100.0,
-                status: "Update completed".to_string(),
-                state: UpdateState::Complete,
-                error: None,
+        info!("Checking updates for app_ids: {:?}", pending);
+        let request = pending.iter().fold(SteamCmdRequest::new("app_info_update 1"), |request, app_id| {
+            request.then(format!("app_info_print {}", app_id))
+        });
+        let output = self
+            .client()?
+            .execute(request.with_timeout(APP_INFO_TIMEOUT))
+            .await?;
+        let mut infos = parser::parse_app_infos(&stdout_text(&output), &pending);
+
+        for app_id in pending {
+            let info = infos
+                .remove(&app_id)
+                .unwrap_or_else(|| Err(Error::SteamError(format!("No app info for {}", app_id))));
+            let comparison = info.and_then(|info| {
+                let manifest = registry.find_app_manifest(app_id)?;
+                compare_with_manifest(app_id, &info, manifest)
             });
-            Ok(())
-        } else {
-            Err(Error::ProcessError("Update failed".to_string()))
+
+            if let Ok(comparison) = &comparison {
+                info!("Caching update status for app_id: {}", app_id);
+                self.update_cache.set(app_id, TimedCacheEntry::new(comparison.clone(), CACHE_TTL_MINUTES));
+            }
+            results.insert(app_id, comparison);
         }
+
+        Ok(results)
     }
 
     pub async fn update_game_with_progress(
         &self,
         app_id: u32,
-        progress_callback: impl Fn(UpdateStatus) + Send + 'static,
+        install_dir: Option<&Path>,
+        cancel: CancelHandle,
+        progress_callback: impl Fn(UpdateStatus) + Send + Sync + 'static,
     ) -> Result<()> {
         info!("Starting update for app_id: {}", app_id);
 
@@ -221,82 +271,51 @@ impl SteamManager {
             progress: 0.0,
             status: "Starting update...".to_string(),
             state: UpdateState::Starting,
-            error: None,
-        });
-
-        let mut cmd = self.build_steam_command();
-        let mut child = cmd
-            .args(&["+app_update", &app_id.to_string(), "validate"])
-            .arg("+quit")
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
-
-        let stdout = child.stdout.take().unwrap();
-        let stderr = child.stderr.take().unwrap();
-
-        let stdout_reader = BufReader::new(stdout);
-        let stderr_reader = BufReader::new(stderr);
-
-        let (tx, mut rx) = mpsc::channel(32);
-        let tx2 = tx.clone();
-
-        // Читаем stdout
-        tokio::spawn(async move {
-            let mut buffer = String::new();
-            let mut reader = stdout_reader;
-            while reader.read_line(&mut buffer).unwrap_or(0) > 0 {
-                if let Ok(status) = parse_update_status(&buffer) {
-                    let _ = tx.send(status).await;
-                }
-                buffer.clear();
-            }
+            ..Default::default()
         });
 
-        // Читаем stderr
-        tokio::spawn(async move {
-            let mut buffer = String::new();
-            let mut reader = stderr_reader;
-            while reader.read_line(&mut buffer).unwrap_or(0) > 0 {
-                if buffer.contains("ERROR") {
-                    let status = UpdateStatus {
+        let progress_callback = Arc::new(progress_callback);
+        let line_callback = progress_callback.clone();
+        let failure = Arc::new(Mutex::new(None::<String>));
+        let failure_slot = failure.clone();
+
+        let mut request = SteamCmdRequest::new(format!("app_update {} validate", app_id))
+            .with_idle_timeout(UPDATE_IDLE_TIMEOUT)
+            .with_cancel(cancel)
+            .on_line(move |line| {
+                let status = match line.stream {
+                    Stream::Stdout => match parse_update_status(&line.text) {
+                        Some(status) => status,
+                        None => return,
+                    },
+                    Stream::Stderr if line.text.contains("ERROR") => UpdateStatus {
                         state: UpdateState::Error,
-                        error: Some(buffer.clone()),
+                        error: Some(line.text.clone()),
                         ..Default::default()
-                    };
-                    let _ = tx2.send(status).await;
-                }
-                buffer.clear();
-            }
-        });
-
-        // Получаем и обрабатываем статусы
-        while let Some(status) = rx.recv().await {
-            progress_callback(status.clone());
-
-            if status.state == UpdateState::Error {
-                error!(
-                    "Error during update for app_id {}: {:?}",
-                    app_id, status.error
-                );
-                return Err(Error::ProcessError(
-                    status.error.unwrap_or_else(|| "Unknown error".into()),
-                ));
-            }
+                    },
+                    Stream::Stderr => return,
+                };
 
-            if status.state == UpdateState::Complete {
-                break;
-            }
+                if status.state == UpdateState::Error {
+                    failure_slot.lock().get_or_insert_with(|| line.text.clone());
+                }
+                line_callback(status);
+            });
+        if let Some(dir) = install_dir {
+            request = request.with_install_dir(dir);
         }
 
-        let status = child
-            .wait()
-            .map_err(|e| Error::ProcessError(e.to_string()))?;
+        let output = self.client()?.execute(request).await?;
 
-        if !status.success() {
+        // В интерактивном режиме кода возврата нет, результат определяем по выводу
+        let failure = failure.lock().take();
+        if let Some(failure) = failure {
+            error!("Error during update for app_id {}: {}", app_id, failure);
+            return Err(parser::failure_error(&failure, Error::UpdateFailed));
+        }
+        if find_line(&output, "Success!").is_none() {
             error!("Update process failed for app_id: {}", app_id);
-            return Err(Error::ProcessError("Update process failed".into()));
+            return Err(Error::UpdateFailed("steamcmd did not confirm the update".into()));
         }
 
         // Инвалидируем кэш после успешного обновления
@@ -309,24 +328,63 @@ impl SteamManager {
             progress: 100.0,
             status: "Update completed".to_string(),
             state: UpdateState::Complete,
-            error: None,
+            ..Default::default()
         });
 
         Ok(())
     }
 
-    pub fn prepare_update(
-        &self,
-        app_id: u32,
-    ) -> Result<Box<dyn Future<Output = Result<()>> + Send + '_>> {
-        Ok(Box::new(self.update_game_with_progress(app_id, |_| {})))
-    }
-
     pub fn refresh_games_list(&self) -> Result<Vec<SteamGame>> {
-        // Очищаем кэш перед обновлением списка
-        // Note: The main Cache doesn't have clear() method, so we'll skip this for now
-        // self.update_cache.clear();
-        // Используем существующий метод для получения списка игр
+        // Список читается из манифестов, поэтому достаточно сбросить кэш обновлений
+        self.update_cache.clear();
         self.get_installed_games()
     }
 }
+
+fn compare_with_manifest(app_id: u32, info: &vdf::Object, manifest: Option<AppManifest>) -> Result<BuildComparison> {
+    let branch = manifest
+        .as_ref()
+        .map(|m| m.branch().to_string())
+        .unwrap_or_else(|| "public".to_string());
+    let remote = parser::parse_branch_build(info, &branch).ok_or_else(|| {
+        Error::SteamError(format!("Branch {} not found for app_id {}", branch, app_id))
+    })?;
+
+    let local_build_id = manifest.map(|m| m.build_id);
+    Ok(BuildComparison {
+        app_id,
+        needs_update: local_build_id != Some(remote.build_id),
+        local_build_id,
+        remote_build_id: remote.build_id,
+        branch,
+        time_updated: remote.time_updated,
+    })
+}
+
+fn stdout_text(lines: &[OutputLine]) -> String {
+    lines
+        .iter()
+        .filter(|line| line.stream == Stream::Stdout)
+        .map(|line| line.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn find_line(lines: &[OutputLine], marker: &str) -> Option<String> {
+    lines
+        .iter()
+        .find(|line| line.text.contains(marker))
+        .map(|line| line.text.clone())
+}
+
+// Если есть учетные данные Steam, используем их, иначе анонимный вход
+fn credentials(settings: &Settings) -> SteamCredentials {
+    for secret in [&settings.steam_username, &settings.steam_password].into_iter().flatten() {
+        register_secret(secret);
+    }
+
+    SteamCredentials {
+        username: settings.steam_username.clone(),
+        password: settings.steam_password.clone(),
+    }
+}
@@ -1,102 +1,423 @@
-use crate::error::{Result, Error};
-use crate::steam::{SteamGame, UpdateState, UpdateStatus};
-use std::path::PathBuf;
-
-pub fn parse_installed_games(output: &str) -> Result<Vec<SteamGame>> {
-    let mut games = Vec::new();
-    let mut current_app_id = None;
-    let mut current_name = None;
-    let mut current_path = None;
-
-    for line in output.lines() {
-        let line = line.trim();
-
-        if line.starts_with("AppID") {
-            if let Some(id) = line.split(':').nth(1) {
-                current_app_id = Some(id.trim().parse::<u32>().unwrap_or(0));
-            }
-        } else if line.starts_with("Name") {
-            if let Some(name) = line.split(':').nth(1) {
-                current_name = Some(name.trim().to_string());
-            }
-        } else if line.starts_with("InstallDir") {
-            if let Some(path) = line.split(':').nth(1) {
-                current_path = Some(PathBuf::from(path.trim()));
-            }
-        }
+use crate::error::{Result, Error, SteamCmdFailure};
+use crate::steam::{UpdateState, UpdateStatus};
+use crate::vdf::{self, Object};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 
-        if let (Some(app_id), Some(name), Some(install_dir)) =
-            (current_app_id, current_name.clone(), current_path.clone())
-        {
-            games.push(SteamGame {
-                app_id,
-                name,
-                install_dir,
-            });
-            current_app_id = None;
-            current_name = None;
-            current_path = None;
-        }
-    }
+#[derive(Debug, Clone)]
+pub struct BranchBuild {
+    pub build_id: u32,
+    pub time_updated: Option<DateTime<Utc>>,
+}
+
+// Вывод +app_info_print: служебные строки steamcmd, затем секция "<appid>" { ... }
+pub fn parse_app_info(output: &str, app_id: u32) -> Result<Object> {
+    let header = format!("\"{}\"", app_id);
+    let start = output
+        .find(&header)
+        .ok_or_else(|| Error::SteamError(format!("No app info in steamcmd output for {}", app_id)))?;
+
+    let block = &output[start..];
+    let end = find_block_end(block)
+        .ok_or_else(|| Error::ParseError(format!("Truncated app info for {}", app_id)))?;
 
-    Ok(games)
+    let root = vdf::parse(&block[..end])?;
+    root.get_object(&app_id.to_string())
+        .cloned()
+        .ok_or_else(|| Error::ParseError(format!("Malformed app info for {}", app_id)))
 }
 
-pub fn check_update_needed(output: &str) -> Result<bool> {
-    for line in output.lines() {
-        if line.contains("Update needed") {
-            return Ok(true);
-        }
-        if line.contains("Up to date") {
-            return Ok(false);
+// Вывод нескольких app_info_print подряд. Перед каждой секцией steamcmd
+// печатает "AppID : <id>, change number : ...", по этим строкам вывод
+// делится на части: id приложения встречается и внутри чужих секций,
+// например как номер общего депо
+pub fn parse_app_infos(output: &str, app_ids: &[u32]) -> HashMap<u32, Result<Object>> {
+    let mut chunks: HashMap<u32, &str> = HashMap::new();
+    let mut current: Option<(u32, usize)> = None;
+    let mut offset = 0;
+
+    for line in output.split_inclusive('\n') {
+        if let Some(app_id) = parse_app_id_line(line) {
+            if let Some((id, start)) = current {
+                chunks.insert(id, &output[start..offset]);
+            }
+            current = Some((app_id, offset));
         }
+        offset += line.len();
     }
-    Err(Error::Unknown(
-        "Could not determine update status".to_string(),
-    ))
+    if let Some((id, start)) = current {
+        chunks.insert(id, &output[start..]);
+    }
+
+    app_ids
+        .iter()
+        .map(|&app_id| {
+            // Без строк AppID ищем секцию во всём выводе
+            let chunk = match chunks.is_empty() {
+                true => Some(output),
+                false => chunks.get(&app_id).copied(),
+            };
+            let info = chunk
+                .ok_or_else(|| Error::SteamError(format!("No app info in steamcmd output for {}", app_id)))
+                .and_then(|chunk| parse_app_info(chunk, app_id));
+            (app_id, info)
+        })
+        .collect()
 }
 
-pub fn parse_download_progress(line: &str) -> Option<(u64, u64)> {
-    if line.contains("Update:") {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 4 {
-            let progress = parts[1].split('/').collect::<Vec<&str>>();
-            if progress.len() == 2 {
-                if let (Ok(current), Ok(total)) =
-                    (progress[0].parse::<u64>(), progress[1].parse::<u64>())
-                {
-                    return Some((current, total));
+// "AppID : 740, change number : 22634712/0, last change : ..."
+fn parse_app_id_line(line: &str) -> Option<u32> {
+    let rest = line.trim_start().strip_prefix("AppID")?.trim_start().strip_prefix(':')?;
+    let end = rest.find(',').unwrap_or(rest.len());
+    rest[..end].trim().parse().ok()
+}
+
+// Конец первой секции верхнего уровня с учётом строк в кавычках
+fn find_block_end(block: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in block.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
                 }
             }
+            _ => {}
         }
     }
+
     None
 }
 
-pub fn parse_update_state(line: &str) -> UpdateState {
-    if line.contains("0x61") {
-        UpdateState::Downloading
-    } else if line.contains("0x81") {
-        UpdateState::Installing
-    } else if line.contains("0x101") {
-        UpdateState::Complete
-    } else if line.contains("0x11") {
-        UpdateState::Starting
-    } else {
-        UpdateState::Unknown
+// depots.branches.<branch>
+pub fn parse_branch_build(info: &Object, branch: &str) -> Option<BranchBuild> {
+    let branch = info
+        .get_object("depots")?
+        .get_object("branches")?
+        .get_object(branch)?;
+
+    let build_id = branch.get("buildid")?.as_u64()?;
+    let time_updated = branch
+        .get("timeupdated")
+        .and_then(|t| t.as_u64())
+        .and_then(|t| i64::try_from(t).ok())
+        .and_then(|t| DateTime::from_timestamp(t, 0));
+
+    Some(BranchBuild {
+        build_id: u32::try_from(build_id).ok()?,
+        time_updated,
+    })
+}
+
+// " Update state (0x61) downloading, progress: 45.12 (1234 / 5678)"
+pub fn parse_download_progress(line: &str) -> Option<(u64, u64)> {
+    let rest = &line[line.find("progress:")? + "progress:".len()..];
+    let bytes = &rest[rest.find('(')? + 1..];
+    let bytes = &bytes[..bytes.find(')')?];
+    let (current, total) = bytes.split_once('/')?;
+    Some((current.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+fn parse_percent(line: &str) -> Option<f32> {
+    let rest = line[line.find("progress:")? + "progress:".len()..].trim_start();
+    let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+// Код состояния из "(0x61)"; сравниваем числа целиком, а не подстроки
+pub fn parse_state_code(line: &str) -> Option<u32> {
+    let rest = &line[line.find("(0x")? + 3..];
+    let code = &rest[..rest.find(')')?];
+    u32::from_str_radix(code, 16).ok()
+}
+
+pub fn parse_update_state(code: u32) -> UpdateState {
+    match code {
+        0x3 => UpdateState::Reconfiguring,
+        0x5 => UpdateState::Validating,
+        0x11 => UpdateState::Preallocating,
+        0x61 => UpdateState::Downloading,
+        0x81 => UpdateState::Verifying,
+        0x101 => UpdateState::Committing,
+        _ => UpdateState::Unknown,
     }
 }
 
-pub fn parse_update_status(line: &str) -> Result<UpdateStatus> {
-    let state = parse_update_state(line);
-    let progress = parse_download_progress(line)
-        .map(|(current, total)| (current as f32 / total as f32) * 100.0)
+// None для строк, не относящихся к ходу обновления
+pub fn parse_update_status(line: &str) -> Option<UpdateStatus> {
+    let text = line.trim();
+
+    if text.starts_with("Success!") {
+        return Some(UpdateStatus {
+            progress: 100.0,
+            status: text.to_string(),
+            state: UpdateState::Complete,
+            ..Default::default()
+        });
+    }
+
+    // "ERROR! Failed to install app '740' (No subscription)",
+    // "Error! App '740' state is 0x602 after update job."
+    if text
+        .get(..6)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("ERROR!"))
+    {
+        return Some(UpdateStatus {
+            status: text.to_string(),
+            state: UpdateState::Error,
+            error: Some(text.to_string()),
+            ..Default::default()
+        });
+    }
+
+    if !text.starts_with("Update state") {
+        return None;
+    }
+
+    let state = parse_state_code(text)
+        .map(parse_update_state)
+        .unwrap_or_default();
+    let bytes = parse_download_progress(text);
+    let progress = parse_percent(text)
+        .or_else(|| {
+            bytes
+                .filter(|(_, total)| *total > 0)
+                .map(|(current, total)| (current as f32 / total as f32) * 100.0)
+        })
         .unwrap_or(0.0);
 
-    Ok(UpdateStatus {
+    Some(UpdateStatus {
         progress,
-        status: line.to_string(),
+        status: text.to_string(),
         state,
         error: None,
+        bytes_downloaded: bytes.map(|(current, _)| current),
+        bytes_total: bytes.map(|(_, total)| total),
     })
 }
+
+const FAILURE_MARKERS: &[(&str, SteamCmdFailure)] = &[
+    ("no subscription", SteamCmdFailure::NoSubscription),
+    ("disk write failure", SteamCmdFailure::DiskWriteFailure),
+    ("not enough disk space", SteamCmdFailure::NotEnoughDiskSpace),
+    ("not enough space", SteamCmdFailure::NotEnoughDiskSpace),
+    ("invalid platform", SteamCmdFailure::InvalidPlatform),
+    ("rate limit exceeded", SteamCmdFailure::RateLimitExceeded),
+    ("invalid password", SteamCmdFailure::InvalidPassword),
+    ("two-factor code mismatch", SteamCmdFailure::TwoFactorMismatch),
+    // EResult Timeout: "Logging in user '...' to Steam Public...FAILED (Timeout)",
+    // "ERROR! Failed to install app '740' (Timeout)"
+    ("(timeout)", SteamCmdFailure::Timeout),
+    ("timeout downloading item", SteamCmdFailure::Timeout),
+    ("connection to steam servers lost", SteamCmdFailure::ConnectionLost),
+    ("no connection", SteamCmdFailure::ConnectionLost),
+];
+
+pub fn classify_failure(text: &str) -> Option<SteamCmdFailure> {
+    let lower = text.to_lowercase();
+
+    if let Some(kind) = FAILURE_MARKERS
+        .iter()
+        .find(|(marker, _)| lower.contains(marker))
+        .map(|(_, kind)| *kind)
+    {
+        return Some(kind);
+    }
+
+    // "state is 0x602 after update job"
+    let rest = &lower[lower.find("state is 0x")? + "state is 0x".len()..];
+    let end = rest.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(rest.len());
+    u32::from_str_radix(&rest[..end], 16)
+        .ok()
+        .map(SteamCmdFailure::UnexpectedState)
+}
+
+// Ошибка с известной причиной или fallback для нераспознанного вывода
+pub fn failure_error(text: &str, fallback: impl FnOnce(String) -> Error) -> Error {
+    match classify_failure(text) {
+        Some(kind) => Error::SteamCmd {
+            kind,
+            message: text.to_string(),
+        },
+        None => fallback(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUCCESS: &str = include_str!("../../tests/fixtures/steamcmd/success.log");
+    const DOWNLOADING: &str = include_str!("../../tests/fixtures/steamcmd/downloading.log");
+    const STATE_0X602: &str = include_str!("../../tests/fixtures/steamcmd/state_0x602.log");
+    const NO_SUBSCRIPTION: &str = include_str!("../../tests/fixtures/steamcmd/no_subscription.log");
+    const DISK_WRITE_FAILURE: &str = include_str!("../../tests/fixtures/steamcmd/disk_write_failure.log");
+    const RATE_LIMIT: &str = include_str!("../../tests/fixtures/steamcmd/rate_limit.log");
+    const INVALID_PASSWORD: &str = include_str!("../../tests/fixtures/steamcmd/invalid_password.log");
+    const APP_INFO_BATCH: &str = include_str!("../../tests/fixtures/steamcmd/app_info_batch.log");
+
+    fn statuses(log: &str) -> Vec<UpdateStatus> {
+        log.lines().filter_map(parse_update_status).collect()
+    }
+
+    // Первая строка с ошибкой, как её выбирают update_game_with_progress и login
+    fn failure_line(log: &str) -> &str {
+        log.lines()
+            .find(|line| line.contains("ERROR") || line.contains("Error!") || line.contains("FAILED"))
+            .expect("fixture has no failure line")
+    }
+
+    #[test]
+    fn success_log() {
+        let statuses = statuses(SUCCESS);
+        let states: Vec<_> = statuses.iter().map(|status| status.state.clone()).collect();
+        assert_eq!(
+            states,
+            [
+                UpdateState::Reconfiguring,
+                UpdateState::Preallocating,
+                UpdateState::Downloading,
+                UpdateState::Downloading,
+                UpdateState::Verifying,
+                UpdateState::Committing,
+                UpdateState::Complete,
+            ]
+        );
+
+        let last = statuses.last().unwrap();
+        assert_eq!(last.progress, 100.0);
+        assert!(last.error.is_none());
+
+        let codes: Vec<_> = SUCCESS.lines().filter_map(parse_state_code).collect();
+        assert_eq!(codes, [0x3, 0x11, 0x61, 0x61, 0x81, 0x101]);
+        assert_eq!(classify_failure(SUCCESS), None);
+    }
+
+    #[test]
+    fn downloading_log_reports_bytes() {
+        let downloading: Vec<_> = statuses(DOWNLOADING)
+            .into_iter()
+            .filter(|status| status.state == UpdateState::Downloading)
+            .collect();
+        assert_eq!(downloading.len(), 4);
+
+        let bytes: Vec<_> = downloading.iter().map(|status| status.bytes_downloaded.unwrap()).collect();
+        assert_eq!(bytes, [0, 191332352, 587202560, 1325400064]);
+        assert!(downloading.iter().all(|status| status.bytes_total == Some(2147483648)));
+        assert_eq!(downloading[3].progress, 61.72);
+
+        assert_eq!(parse_state_code(DOWNLOADING.lines().last().unwrap()), Some(0x61));
+        assert_eq!(classify_failure(DOWNLOADING), None);
+    }
+
+    #[test]
+    fn state_0x602_log() {
+        let last = statuses(STATE_0X602).pop().unwrap();
+        assert_eq!(last.state, UpdateState::Error);
+        assert_eq!(last.error.as_deref(), Some("Error! App '740' state is 0x602 after update job."));
+
+        let line = failure_line(STATE_0X602);
+        // "state is 0x602" без скобок не код состояния из "Update state (0x..)"
+        assert_eq!(parse_state_code(line), None);
+        assert_eq!(classify_failure(line), Some(SteamCmdFailure::UnexpectedState(0x602)));
+        assert!(classify_failure(line).unwrap().is_retryable());
+    }
+
+    #[test]
+    fn no_subscription_log() {
+        let last = statuses(NO_SUBSCRIPTION).pop().unwrap();
+        assert_eq!(last.state, UpdateState::Error);
+
+        let line = failure_line(NO_SUBSCRIPTION);
+        assert_eq!(parse_state_code(line), None);
+        assert_eq!(classify_failure(line), Some(SteamCmdFailure::NoSubscription));
+        assert!(!SteamCmdFailure::NoSubscription.is_retryable());
+    }
+
+    #[test]
+    fn disk_write_failure_log() {
+        let statuses = statuses(DISK_WRITE_FAILURE);
+        assert_eq!(statuses[statuses.len() - 2].state, UpdateState::Committing);
+        assert_eq!(statuses.last().unwrap().state, UpdateState::Error);
+
+        let line = failure_line(DISK_WRITE_FAILURE);
+        assert_eq!(classify_failure(line), Some(SteamCmdFailure::DiskWriteFailure));
+    }
+
+    #[test]
+    fn rate_limit_log() {
+        // Ошибка входа - не строка хода обновления
+        assert!(statuses(RATE_LIMIT).is_empty());
+
+        let line = failure_line(RATE_LIMIT);
+        assert_eq!(parse_state_code(line), None);
+        assert_eq!(classify_failure(line), Some(SteamCmdFailure::RateLimitExceeded));
+        assert!(SteamCmdFailure::RateLimitExceeded.is_retryable());
+    }
+
+    #[test]
+    fn invalid_password_log() {
+        assert!(statuses(INVALID_PASSWORD).is_empty());
+
+        let line = failure_line(INVALID_PASSWORD);
+        assert_eq!(classify_failure(line), Some(SteamCmdFailure::InvalidPassword));
+        assert!(!SteamCmdFailure::InvalidPassword.is_retryable());
+    }
+
+    #[test]
+    fn timeout_markers_match_steamcmd_messages() {
+        for line in [
+            "Logging in user 'player' to Steam Public...FAILED (Timeout)",
+            "ERROR! Failed to install app '740' (Timeout)",
+            "ERROR! Timeout downloading item 2503432013",
+        ] {
+            assert_eq!(classify_failure(line), Some(SteamCmdFailure::Timeout), "{}", line);
+        }
+
+        // Слово timeout вне сообщения об ошибке steamcmd
+        assert_eq!(classify_failure("ERROR! Failed to load config/timeouts.vdf"), None);
+        assert_eq!(classify_failure("Error! App 'Timeout Protocol' is not installed"), None);
+    }
+
+    #[test]
+    fn batch_app_info_is_split_by_app() {
+        let mut infos = parse_app_infos(APP_INFO_BATCH, &[228988, 730, 440]);
+
+        // "228988" встречается раньше как депо в секции 730
+        let redist = infos.remove(&228988).unwrap().unwrap();
+        assert_eq!(parse_branch_build(&redist, "public").unwrap().build_id, 12999000);
+
+        let cs = infos.remove(&730).unwrap().unwrap();
+        let public = parse_branch_build(&cs, "public").unwrap();
+        assert_eq!(public.build_id, 14533481);
+        assert_eq!(public.time_updated.unwrap().timestamp(), 1718123456);
+        assert_eq!(parse_branch_build(&cs, "beta").unwrap().build_id, 14533500);
+
+        assert!(infos.remove(&440).unwrap().is_err());
+    }
+
+    #[test]
+    fn failure_error_keeps_unknown_text() {
+        let error = failure_error("ERROR! Something unexpected", Error::UpdateFailed);
+        assert!(matches!(error, Error::UpdateFailed(message) if message == "ERROR! Something unexpected"));
+
+        let error = failure_error(failure_line(NO_SUBSCRIPTION), Error::UpdateFailed);
+        assert!(matches!(error, Error::SteamCmd { kind: SteamCmdFailure::NoSubscription, .. }));
+    }
+}
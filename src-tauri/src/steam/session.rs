@@ -0,0 +1,454 @@
+use crate::error::{Error, Result, SteamCmdFailure};
+use crate::process;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+const READ_BUFFER_SIZE: usize = 4096;
+const LINE_CHANNEL_SIZE: usize = 256;
+// Приглашения steamcmd выводятся без перевода строки
+const PROMPTS: &[(&str, Prompt)] = &[
+    ("Steam>", Prompt::Command),
+    ("Steam Guard code:", Prompt::SteamGuard),
+    ("Two-factor code:", Prompt::TwoFactor),
+    ("password:", Prompt::Password),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Command,
+    SteamGuard,
+    TwoFactor,
+    Password,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputLine {
+    pub stream: Stream,
+    pub text: String,
+}
+
+impl OutputLine {
+    pub fn prompt(&self) -> Option<Prompt> {
+        if self.stream != Stream::Stdout {
+            return None;
+        }
+        PROMPTS
+            .iter()
+            .find(|(prompt, _)| self.text.ends_with(prompt))
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn is_prompt(&self) -> bool {
+        self.prompt() == Some(Prompt::Command)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionOutput {
+    pub status: ExitStatus,
+    pub lines: Vec<OutputLine>,
+}
+
+impl SessionOutput {
+    pub fn stdout(&self) -> String {
+        self.lines
+            .iter()
+            .filter(|line| line.stream == Stream::Stdout)
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Отмена из другой задачи; клоны ссылаются на одну и ту же сессию
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    sender: watch::Sender<bool>,
+}
+
+impl Default for CancelHandle {
+    fn default() -> Self {
+        Self {
+            sender: watch::channel(false).0,
+        }
+    }
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    pub async fn cancelled(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+// Запущенный steamcmd: построчный вывод stdout и stderr без блокировки рантайма
+pub struct SteamCmdSession {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: mpsc::Receiver<OutputLine>,
+    cancel: watch::Receiver<bool>,
+    cancel_handle: CancelHandle,
+    deadline: Option<Instant>,
+    idle_timeout: Option<Duration>,
+}
+
+impl SteamCmdSession {
+    pub fn spawn(cmd: Command) -> Result<Self> {
+        Self::start(cmd, false)
+    }
+
+    // Команды передаются через stdin, завершение каждой определяется по приглашению
+    pub fn spawn_interactive(cmd: Command) -> Result<Self> {
+        Self::start(cmd, true)
+    }
+
+    fn start(mut cmd: Command, interactive: bool) -> Result<Self> {
+        let stdin = if interactive { Stdio::piped() } else { Stdio::null() };
+        let mut child = process::spawn_group(&mut cmd)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| Error::ProcessError(format!("Failed to start steamcmd: {}", e)))?;
+
+        let (tx, lines) = mpsc::channel(LINE_CHANNEL_SIZE);
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(read_lines(stdout, Stream::Stdout, tx.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(read_lines(stderr, Stream::Stderr, tx));
+        }
+
+        let cancel_handle = CancelHandle::default();
+
+        Ok(Self {
+            stdin: child.stdin.take(),
+            child,
+            lines,
+            cancel: cancel_handle.sender.subscribe(),
+            cancel_handle,
+            deadline: None,
+            idle_timeout: None,
+        })
+    }
+
+    // Ограничение на всё время работы процесса
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    // Ограничение на паузу между строками: загрузка может идти часами,
+    // но steamcmd регулярно печатает прогресс
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    // Для интерактивной сессии ограничения задаются на каждую команду
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    pub async fn send_command(&mut self, command: &str) -> Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| Error::ProcessError("steamcmd session is not interactive".to_string()))?;
+
+        let line = format!("{}\n", command);
+        stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| Error::ProcessError(format!("Failed to write to steamcmd: {}", e)))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| Error::ProcessError(format!("Failed to write to steamcmd: {}", e)))
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel_handle.clone()
+    }
+
+    // Отмена отдельного запроса в долгоживущей сессии: процесс завершается
+    // по внешнему handle, пока он не будет заменён
+    pub fn set_cancel_handle(&mut self, handle: CancelHandle) {
+        self.cancel = handle.sender.subscribe();
+        self.cancel_handle = handle;
+    }
+
+    // None после закрытия обоих потоков. При таймауте или отмене процесс завершается
+    pub async fn next_line(&mut self) -> Result<Option<OutputLine>> {
+        if self.cancel_handle.is_cancelled() {
+            self.kill().await;
+            return Err(Error::Cancelled);
+        }
+
+        let now = Instant::now();
+        let limit = match (self.deadline, self.idle_timeout) {
+            (Some(deadline), Some(idle)) => Some(deadline.min(now + idle)),
+            (deadline, idle) => deadline.or(idle.map(|idle| now + idle)),
+        };
+
+        let sleep = async {
+            match limit {
+                Some(limit) => tokio::time::sleep_until(limit).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let cancelled = tokio::select! {
+            line = self.lines.recv() => return Ok(line),
+            _ = self.cancel.wait_for(|cancelled| *cancelled) => true,
+            _ = sleep => false,
+        };
+
+        self.kill().await;
+        if cancelled {
+            return Err(Error::Cancelled);
+        }
+
+        let reason = if self.deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            "steamcmd timed out"
+        } else {
+            "steamcmd stopped responding"
+        };
+        Err(Error::SteamCmd {
+            kind: SteamCmdFailure::Timeout,
+            message: reason.to_string(),
+        })
+    }
+
+    pub async fn wait(mut self) -> Result<ExitStatus> {
+        // Без stdin интерактивный steamcmd завершается
+        self.stdin = None;
+
+        // Дочитываем вывод, иначе процесс может зависнуть на заполненном канале
+        while self.next_line().await?.is_some() {}
+
+        self.child
+            .wait()
+            .await
+            .map_err(|e| Error::ProcessError(e.to_string()))
+    }
+
+    // Запуск с накоплением всего вывода, для коротких команд
+    pub async fn collect(mut self) -> Result<SessionOutput> {
+        let mut lines = Vec::new();
+        while let Some(line) = self.next_line().await? {
+            lines.push(line);
+        }
+        let status = self.wait().await?;
+        Ok(SessionOutput { status, lines })
+    }
+
+    async fn kill(&mut self) {
+        if let Err(e) = process::kill_tree(&mut self.child).await {
+            warn!("Failed to kill steamcmd: {}", e);
+        }
+    }
+}
+
+// steamcmd обновляет строку прогресса через '\r', поэтому он тоже считается концом строки
+async fn read_lines<R>(mut reader: R, stream: Stream, tx: mpsc::Sender<OutputLine>)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buffer = [0u8; READ_BUFFER_SIZE];
+    let mut pending = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) => {
+                debug!("steamcmd {:?} read error: {}", stream, e);
+                break;
+            }
+        };
+
+        for &byte in &buffer[..read] {
+            if byte == b'\r' || byte == b'\n' {
+                if !send_line(&tx, stream, &mut pending).await {
+                    return;
+                }
+            } else {
+                pending.push(byte);
+            }
+        }
+
+        // Приглашение не завершается переводом строки, отдаём его сразу
+        if ends_with_prompt(&pending) && !send_line(&tx, stream, &mut pending).await {
+            return;
+        }
+    }
+
+    send_line(&tx, stream, &mut pending).await;
+}
+
+// Пустые строки (в том числе между '\r' и '\n') пропускаются
+async fn send_line(tx: &mpsc::Sender<OutputLine>, stream: Stream, pending: &mut Vec<u8>) -> bool {
+    if pending.is_empty() {
+        return true;
+    }
+
+    let text = strip_ansi(&String::from_utf8_lossy(pending)).trim_end().to_string();
+    pending.clear();
+    if text.is_empty() {
+        return true;
+    }
+
+    tx.send(OutputLine { stream, text }).await.is_ok()
+}
+
+fn ends_with_prompt(pending: &[u8]) -> bool {
+    let trimmed = pending.trim_ascii_end();
+    PROMPTS
+        .iter()
+        .any(|(prompt, _)| trimmed.ends_with(prompt.as_bytes()))
+}
+
+// steamcmd в терминальном режиме окрашивает вывод escape-последовательностями
+fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            result.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", script]);
+        cmd
+    }
+
+    fn texts(lines: &[OutputLine], stream: Stream) -> Vec<&str> {
+        lines
+            .iter()
+            .filter(|line| line.stream == stream)
+            .map(|line| line.text.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn progress_lines_are_split_on_carriage_return() {
+        let script = r"printf ' Update state (0x61) downloading, progress: 10.00\r Update state (0x61) downloading, progress: 55.50\r\nSuccess! App '\''570'\'' fully installed.\n'; printf 'warning\n' >&2";
+        let output = SteamCmdSession::spawn(shell(script)).unwrap().collect().await.unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            texts(&output.lines, Stream::Stdout),
+            vec![
+                " Update state (0x61) downloading, progress: 10.00",
+                " Update state (0x61) downloading, progress: 55.50",
+                "Success! App '570' fully installed.",
+            ]
+        );
+        assert_eq!(texts(&output.lines, Stream::Stderr), vec!["warning"]);
+    }
+
+    #[tokio::test]
+    async fn prompt_without_newline_is_delivered() {
+        let script = r#"printf 'Loading Steam API...OK\nSteam>'; read cmd; printf 'got %s\npassword: ' "$cmd"; read cmd"#;
+        let mut session = SteamCmdSession::spawn_interactive(shell(script)).unwrap();
+
+        let line = session.next_line().await.unwrap().unwrap();
+        assert_eq!(line.text, "Loading Steam API...OK");
+        assert_eq!(line.prompt(), None);
+
+        let line = session.next_line().await.unwrap().unwrap();
+        assert_eq!(line.text, "Steam>");
+        assert!(line.is_prompt());
+
+        session.send_command("login user").await.unwrap();
+        assert_eq!(session.next_line().await.unwrap().unwrap().text, "got login user");
+        let line = session.next_line().await.unwrap().unwrap();
+        assert_eq!(line.prompt(), Some(Prompt::Password));
+
+        session.send_command("secret").await.unwrap();
+        assert!(session.wait().await.unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn colors_are_stripped() {
+        let script = r"printf '\033[0;32mSuccess!\033[0m App installed\n'";
+        let output = SteamCmdSession::spawn(shell(script)).unwrap().collect().await.unwrap();
+        assert_eq!(output.stdout(), "Success! App installed");
+
+        assert_eq!(strip_ansi("\u{1b}[1m\u{1b}[31mERROR\u{1b}[0m!"), "ERROR!");
+        assert_eq!(strip_ansi("no colors"), "no colors");
+    }
+
+    #[tokio::test]
+    async fn silent_process_hits_idle_timeout() {
+        let mut session = SteamCmdSession::spawn(shell("printf 'start\\n'; sleep 60"))
+            .unwrap()
+            .with_idle_timeout(Duration::from_millis(200));
+
+        assert_eq!(session.next_line().await.unwrap().unwrap().text, "start");
+        let err = session.next_line().await.unwrap_err();
+        assert!(
+            matches!(err, Error::SteamCmd { kind: SteamCmdFailure::Timeout, .. }),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_session() {
+        let mut session = SteamCmdSession::spawn(shell("sleep 60")).unwrap();
+        let cancel = session.cancel_handle();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel.cancel();
+        });
+
+        let result = tokio::time::timeout(Duration::from_secs(10), session.next_line()).await.unwrap();
+        assert!(matches!(result, Err(Error::Cancelled)));
+        // После отмены сессия больше не читает вывод
+        assert!(matches!(session.next_line().await, Err(Error::Cancelled)));
+    }
+}
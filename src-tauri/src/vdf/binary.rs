@@ -0,0 +1,672 @@
+use super::{Object, Value};
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::ops::Range;
+
+const TYPE_OBJECT: u8 = 0x00;
+const TYPE_STRING: u8 = 0x01;
+const TYPE_INT32: u8 = 0x02;
+const TYPE_FLOAT32: u8 = 0x03;
+const TYPE_POINTER: u8 = 0x04;
+const TYPE_WIDE_STRING: u8 = 0x05;
+const TYPE_COLOR: u8 = 0x06;
+const TYPE_UINT64: u8 = 0x07;
+const TYPE_END: u8 = 0x08;
+const TYPE_INT64: u8 = 0x0A;
+const TYPE_END_ALT: u8 = 0x0B;
+
+// Версии appcache/appinfo.vdf
+const APPINFO_V27: u32 = 0x0756_4427;
+const APPINFO_V28: u32 = 0x0756_4428;
+const APPINFO_V29: u32 = 0x0756_4429;
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::ParseError(format!("binary VDF offset {}: {}", self.pos, message))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.read_bytes(N)?);
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        self.read_array().map(i32::from_le_bytes)
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    fn read_i64(&mut self) -> Result<i64> {
+        self.read_array().map(i64::from_le_bytes)
+    }
+
+    fn read_f32(&mut self) -> Result<f32> {
+        self.read_array().map(f32::from_le_bytes)
+    }
+
+    fn read_cstr(&mut self) -> Result<String> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| self.error("unterminated string"))?;
+        let value = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.pos += len + 1;
+        Ok(value)
+    }
+
+    fn read_wide_str(&mut self) -> Result<String> {
+        let mut units = Vec::new();
+        loop {
+            match u16::from_le_bytes(self.read_array()?) {
+                0 => break,
+                unit => units.push(unit),
+            }
+        }
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+struct BinaryParser<'a> {
+    cursor: Cursor<'a>,
+    // В appinfo v29 ключи заменены индексами в таблице строк
+    keys: Option<&'a [String]>,
+}
+
+impl<'a> BinaryParser<'a> {
+    fn read_key(&mut self) -> Result<String> {
+        match self.keys {
+            Some(table) => {
+                let index = self.cursor.read_u32()? as usize;
+                table
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| self.cursor.error(&format!("string table index {} out of range", index)))
+            }
+            None => self.cursor.read_cstr(),
+        }
+    }
+
+    fn read_object(&mut self, nested: bool) -> Result<Object> {
+        let mut object = Object::new();
+
+        loop {
+            if !nested && self.cursor.is_empty() {
+                return Ok(object);
+            }
+
+            let kind = self.cursor.read_u8()?;
+            if kind == TYPE_END || kind == TYPE_END_ALT {
+                return Ok(object);
+            }
+
+            let key = self.read_key()?;
+            let value = match kind {
+                TYPE_OBJECT => Value::Object(self.read_object(true)?),
+                TYPE_STRING => Value::String(self.cursor.read_cstr()?),
+                TYPE_INT32 => Value::Int32(self.cursor.read_i32()?),
+                TYPE_FLOAT32 => Value::Float32(self.cursor.read_f32()?),
+                TYPE_POINTER => Value::Pointer(self.cursor.read_u32()?),
+                TYPE_WIDE_STRING => Value::String(self.cursor.read_wide_str()?),
+                TYPE_COLOR => Value::Color(self.cursor.read_u32()?),
+                TYPE_UINT64 => Value::UInt64(self.cursor.read_u64()?),
+                TYPE_INT64 => Value::Int64(self.cursor.read_i64()?),
+                other => {
+                    return Err(self
+                        .cursor
+                        .error(&format!("unknown value type 0x{:02x} for key \"{}\"", other, key)))
+                }
+            };
+
+            object.push(key, value);
+        }
+    }
+}
+
+// Бинарный KeyValues без заголовка (shortcuts.vdf, localconfig и т.п.)
+pub fn parse_binary(data: &[u8]) -> Result<Object> {
+    BinaryParser {
+        cursor: Cursor::new(data),
+        keys: None,
+    }
+    .read_object(false)
+}
+
+#[derive(Debug, Clone)]
+pub struct AppInfo {
+    pub app_id: u32,
+    pub info_state: u32,
+    pub last_updated: u32,
+    pub pics_token: u64,
+    pub change_number: u32,
+    pub data: Object,
+}
+
+impl AppInfo {
+    fn root(&self) -> &Object {
+        self.data.get_object("appinfo").unwrap_or(&self.data)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.root().get_object("common")?.get_str("name")
+    }
+
+    fn branch(&self, branch: &str) -> Option<&Object> {
+        self.root()
+            .get_object("depots")?
+            .get_object("branches")?
+            .get_object(branch)
+    }
+
+    // depots.branches.<branch>.buildid
+    pub fn build_id(&self, branch: &str) -> Option<u32> {
+        self.branch(branch)?
+            .get("buildid")?
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+    }
+
+    pub fn branch_time_updated(&self, branch: &str) -> Option<i64> {
+        self.branch(branch)?
+            .get("timeupdated")?
+            .as_u64()
+            .and_then(|t| i64::try_from(t).ok())
+    }
+}
+
+struct AppInfoReader<'a> {
+    cursor: Cursor<'a>,
+    version: u32,
+    strings: Option<Vec<String>>,
+}
+
+impl<'a> AppInfoReader<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(data);
+        let version = cursor.read_u32()?;
+        if !matches!(version, APPINFO_V27 | APPINFO_V28 | APPINFO_V29) {
+            return Err(cursor.error(&format!("unsupported appinfo version 0x{:08x}", version)));
+        }
+        let _universe = cursor.read_u32()?;
+
+        let strings = if version >= APPINFO_V29 {
+            let offset = cursor.read_i64()?;
+            let offset = usize::try_from(offset)
+                .ok()
+                .filter(|o| *o <= data.len())
+                .ok_or_else(|| cursor.error("invalid string table offset"))?;
+            Some(read_string_table(&data[offset..])?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            cursor,
+            version,
+            strings,
+        })
+    }
+
+    // Возвращает appid и положение следующей записи, не разбирая её
+    fn next_entry(&mut self) -> Result<Option<(u32, Range<usize>)>> {
+        let app_id = self.cursor.read_u32()?;
+        if app_id == 0 {
+            return Ok(None);
+        }
+
+        let size = self.cursor.read_u32()? as usize;
+        let start = self.cursor.pos;
+        self.cursor.read_bytes(size)?;
+        Ok(Some((app_id, start..self.cursor.pos)))
+    }
+
+    fn parse_entry(&self, app_id: u32, entry: &[u8]) -> Result<AppInfo> {
+        parse_entry(app_id, entry, self.version, self.strings.as_deref())
+    }
+}
+
+fn parse_entry(app_id: u32, entry: &[u8], version: u32, strings: Option<&[String]>) -> Result<AppInfo> {
+    let mut entry = Cursor::new(entry);
+    let info_state = entry.read_u32()?;
+    let last_updated = entry.read_u32()?;
+    let pics_token = entry.read_u64()?;
+    let _sha1 = entry.read_bytes(20)?;
+    let change_number = entry.read_u32()?;
+    if version >= APPINFO_V28 {
+        let _binary_sha1 = entry.read_bytes(20)?;
+    }
+
+    let mut parser = BinaryParser {
+        cursor: entry,
+        keys: strings,
+    };
+    let data = parser.read_object(false)?;
+
+    Ok(AppInfo {
+        app_id,
+        info_state,
+        last_updated,
+        pics_token,
+        change_number,
+        data,
+    })
+}
+
+fn read_string_table(data: &[u8]) -> Result<Vec<String>> {
+    let mut cursor = Cursor::new(data);
+    let count = cursor.read_u32()? as usize;
+    // Не доверяем счётчику при резервировании памяти
+    let mut strings = Vec::with_capacity(count.min(data.len()));
+    for _ in 0..count {
+        strings.push(cursor.read_cstr()?);
+    }
+    Ok(strings)
+}
+
+pub fn read_appinfo(data: &[u8]) -> Result<Vec<AppInfo>> {
+    let mut reader = AppInfoReader::new(data)?;
+    let mut apps = Vec::new();
+    while let Some((app_id, range)) = reader.next_entry()? {
+        apps.push(reader.parse_entry(app_id, &data[range])?);
+    }
+    Ok(apps)
+}
+
+// Пропускает остальные записи без разбора, appinfo.vdf бывает больше 100 МБ
+pub fn find_appinfo(data: &[u8], app_id: u32) -> Result<Option<AppInfo>> {
+    let mut reader = AppInfoReader::new(data)?;
+    while let Some((id, range)) = reader.next_entry()? {
+        if id == app_id {
+            return reader.parse_entry(app_id, &data[range]).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+// Положение записей appinfo.vdf и таблица строк v29: по нему отдельные
+// записи читаются из файла без повторного прохода по всем остальным
+#[derive(Debug, Clone)]
+pub struct AppInfoIndex {
+    version: u32,
+    strings: Option<Vec<String>>,
+    entries: HashMap<u32, Range<usize>>,
+}
+
+impl AppInfoIndex {
+    pub fn build(data: &[u8]) -> Result<Self> {
+        let mut reader = AppInfoReader::new(data)?;
+        let mut entries = HashMap::new();
+        while let Some((app_id, range)) = reader.next_entry()? {
+            entries.entry(app_id).or_insert(range);
+        }
+        Ok(Self {
+            version: reader.version,
+            strings: reader.strings,
+            entries,
+        })
+    }
+
+    // Байты записи в файле, из которого построен индекс
+    pub fn entry_range(&self, app_id: u32) -> Option<Range<usize>> {
+        self.entries.get(&app_id).cloned()
+    }
+
+    pub fn parse_entry(&self, app_id: u32, entry: &[u8]) -> Result<AppInfo> {
+        parse_entry(app_id, entry, self.version, self.strings.as_deref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::steam::Shortcut;
+
+    // Сборка бинарного KeyValues: ключи строками или индексами таблицы строк (v29)
+    #[derive(Default)]
+    struct Writer {
+        bytes: Vec<u8>,
+        strings: Option<Vec<String>>,
+    }
+
+    impl Writer {
+        fn with_string_table() -> Self {
+            Self {
+                bytes: Vec::new(),
+                strings: Some(Vec::new()),
+            }
+        }
+
+        fn key(&mut self, kind: u8, key: &str) -> &mut Self {
+            self.bytes.push(kind);
+            match &mut self.strings {
+                Some(strings) => {
+                    let index = strings.iter().position(|s| s == key).unwrap_or_else(|| {
+                        strings.push(key.to_string());
+                        strings.len() - 1
+                    });
+                    self.bytes.extend((index as u32).to_le_bytes());
+                }
+                None => self.cstr(key),
+            }
+            self
+        }
+
+        fn cstr(&mut self, value: &str) {
+            self.bytes.extend(value.as_bytes());
+            self.bytes.push(0);
+        }
+
+        fn string(&mut self, key: &str, value: &str) -> &mut Self {
+            self.key(TYPE_STRING, key).cstr(value);
+            self
+        }
+
+        fn int32(&mut self, key: &str, value: i32) -> &mut Self {
+            self.key(TYPE_INT32, key).bytes.extend(value.to_le_bytes());
+            self
+        }
+
+        fn open(&mut self, key: &str) -> &mut Self {
+            self.key(TYPE_OBJECT, key)
+        }
+
+        fn close(&mut self) -> &mut Self {
+            self.bytes.push(TYPE_END);
+            self
+        }
+    }
+
+    // appinfo.vdf steam: 570 с веткой public, 730 без веток
+    fn app_data(writer: &mut Writer, app_id: u32, name: &str, build_id: &str) {
+        writer
+            .open("appinfo")
+            .int32("appid", app_id as i32)
+            .open("common")
+            .string("name", name)
+            .close()
+            .open("depots")
+            .open("branches")
+            .open("public")
+            .string("buildid", build_id)
+            .string("timeupdated", "1700000000")
+            .close()
+            .open("beta")
+            .int32("buildid", 12600000)
+            .close()
+            .close()
+            .close()
+            .close()
+            .close();
+    }
+
+    fn entry(version: u32, app_id: u32, data: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend(2u32.to_le_bytes()); // info_state
+        payload.extend(1700000000u32.to_le_bytes()); // last_updated
+        payload.extend(0x1234u64.to_le_bytes()); // pics_token
+        payload.extend([0xAA; 20]); // sha1
+        payload.extend(42u32.to_le_bytes()); // change_number
+        if version >= APPINFO_V28 {
+            payload.extend([0xBB; 20]);
+        }
+        payload.extend(data);
+
+        let mut bytes = app_id.to_le_bytes().to_vec();
+        bytes.extend((payload.len() as u32).to_le_bytes());
+        bytes.extend(payload);
+        bytes
+    }
+
+    fn appinfo(version: u32, apps: &[(u32, &str, &str)]) -> Vec<u8> {
+        let mut writer = match version {
+            APPINFO_V29 => Writer::with_string_table(),
+            _ => Writer::default(),
+        };
+
+        let mut entries = Vec::new();
+        for (app_id, name, build_id) in apps {
+            writer.bytes.clear();
+            app_data(&mut writer, *app_id, name, build_id);
+            entries.extend(entry(version, *app_id, &writer.bytes));
+        }
+        entries.extend(0u32.to_le_bytes());
+
+        let mut bytes = version.to_le_bytes().to_vec();
+        bytes.extend(1u32.to_le_bytes()); // universe
+        if let Some(strings) = &writer.strings {
+            let offset = bytes.len() + 8 + entries.len();
+            bytes.extend((offset as i64).to_le_bytes());
+            bytes.extend(entries);
+            bytes.extend((strings.len() as u32).to_le_bytes());
+            for string in strings {
+                bytes.extend(string.as_bytes());
+                bytes.push(0);
+            }
+        } else {
+            bytes.extend(entries);
+        }
+        bytes
+    }
+
+    const APPS: &[(u32, &str, &str)] = &[(730, "Counter-Strike 2", "13000000"), (570, "Dota 2", "12500000")];
+
+    #[test]
+    fn all_value_types_are_parsed() {
+        let mut writer = Writer::default();
+        writer.open("root").string("name", "Dota 2").int32("int", -5);
+        writer.key(TYPE_FLOAT32, "float").bytes.extend(1.5f32.to_le_bytes());
+        writer.key(TYPE_POINTER, "ptr").bytes.extend(7u32.to_le_bytes());
+        writer.key(TYPE_COLOR, "color").bytes.extend(0xFF00FFu32.to_le_bytes());
+        writer.key(TYPE_UINT64, "u64").bytes.extend(u64::MAX.to_le_bytes());
+        writer.key(TYPE_INT64, "i64").bytes.extend((-1i64).to_le_bytes());
+        writer.key(TYPE_WIDE_STRING, "wide");
+        writer.bytes.extend("Дота".encode_utf16().chain([0]).flat_map(u16::to_le_bytes));
+        writer.open("empty").bytes.push(TYPE_END_ALT);
+        writer.close().close();
+
+        let root = parse_binary(&writer.bytes).unwrap();
+        let root = root.get_object("root").unwrap();
+        assert_eq!(root.get_str("name"), Some("Dota 2"));
+        assert_eq!(root.get("int"), Some(&Value::Int32(-5)));
+        assert_eq!(root.get("float"), Some(&Value::Float32(1.5)));
+        assert_eq!(root.get("ptr"), Some(&Value::Pointer(7)));
+        assert_eq!(root.get("color"), Some(&Value::Color(0xFF00FF)));
+        assert_eq!(root.get("u64"), Some(&Value::UInt64(u64::MAX)));
+        assert_eq!(root.get("i64"), Some(&Value::Int64(-1)));
+        assert_eq!(root.get_str("wide"), Some("Дота"));
+        assert!(root.get_object("empty").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_binary_is_rejected() {
+        let mut writer = Writer::default();
+        writer.open("root").string("name", "Dota 2");
+        let data = writer.bytes.clone();
+
+        // Нет закрывающего байта секции
+        assert!(parse_binary(&data).is_err());
+        // Строка обрезана
+        assert!(parse_binary(&data[..data.len() - 3]).is_err());
+        // Число обрезано
+        writer.int32("int", 1);
+        assert!(parse_binary(&writer.bytes[..writer.bytes.len() - 2]).is_err());
+
+        let mut unknown = Writer::default();
+        unknown.key(0x09, "odd").bytes.extend([0, 0, 0, 0]);
+        match parse_binary(&unknown.bytes) {
+            Err(Error::ParseError(message)) => assert!(message.contains("unknown value type 0x09"), "{}", message),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn shortcuts_are_deserialized() {
+        let mut writer = Writer::default();
+        writer
+            .open("shortcuts")
+            .open("0")
+            .int32("appid", -1_234_567_890)
+            .string("AppName", "Heroic")
+            .string("Exe", "\"/usr/bin/heroic\"")
+            .string("StartDir", "\"/usr/bin/\"")
+            .int32("IsHidden", 0)
+            .int32("LastPlayTime", 1700000000)
+            .open("tags")
+            .string("0", "favorite")
+            .close()
+            .close()
+            .open("1")
+            .string("appname", "Lutris")
+            .int32("IsHidden", 1)
+            .close()
+            .close()
+            .close();
+
+        let shortcuts: Vec<Shortcut> = crate::vdf::from_binary(&writer.bytes).unwrap();
+        assert_eq!(shortcuts.len(), 2);
+        assert_eq!(shortcuts[0].app_id, -1_234_567_890);
+        assert_eq!(shortcuts[0].app_name, "Heroic");
+        assert_eq!(shortcuts[0].exe, "\"/usr/bin/heroic\"");
+        assert_eq!(shortcuts[0].last_play_time, 1700000000);
+        assert_eq!(shortcuts[0].tags, vec!["favorite"]);
+        assert!(!shortcuts[0].is_hidden);
+        assert_eq!(shortcuts[1].app_name, "Lutris");
+        assert!(shortcuts[1].is_hidden);
+    }
+
+    #[test]
+    fn appinfo_versions_are_read() {
+        for version in [APPINFO_V27, APPINFO_V28, APPINFO_V29] {
+            let apps = read_appinfo(&appinfo(version, APPS)).unwrap();
+            assert_eq!(apps.len(), 2, "version {:x}", version);
+
+            let dota = &apps[1];
+            assert_eq!(dota.app_id, 570);
+            assert_eq!(dota.info_state, 2);
+            assert_eq!(dota.last_updated, 1700000000);
+            assert_eq!(dota.pics_token, 0x1234);
+            assert_eq!(dota.change_number, 42);
+            assert_eq!(dota.name(), Some("Dota 2"));
+            assert_eq!(apps[0].name(), Some("Counter-Strike 2"));
+        }
+    }
+
+    #[test]
+    fn build_id_is_read_from_branch() {
+        let info = find_appinfo(&appinfo(APPINFO_V28, APPS), 570).unwrap().unwrap();
+
+        assert_eq!(info.build_id("public"), Some(12500000));
+        // В бинарном формате buildid может быть числом
+        assert_eq!(info.build_id("beta"), Some(12600000));
+        assert_eq!(info.build_id("missing"), None);
+        assert_eq!(info.branch_time_updated("public"), Some(1700000000));
+        assert_eq!(info.branch_time_updated("beta"), None);
+    }
+
+    #[test]
+    fn find_appinfo_skips_other_entries() {
+        // Запись 730 повреждена, но её данные не разбираются
+        let mut data = APPINFO_V28.to_le_bytes().to_vec();
+        data.extend(1u32.to_le_bytes());
+        data.extend(entry(APPINFO_V28, 730, &[0x09, 0xFF]));
+        data.extend(&appinfo(APPINFO_V28, &APPS[1..])[8..]);
+
+        assert_eq!(find_appinfo(&data, 570).unwrap().unwrap().name(), Some("Dota 2"));
+        assert!(find_appinfo(&data, 440).unwrap().is_none());
+        assert!(read_appinfo(&data).is_err());
+    }
+
+    #[test]
+    fn index_reads_single_entries() {
+        for version in [APPINFO_V28, APPINFO_V29] {
+            let data = appinfo(version, APPS);
+            let index = AppInfoIndex::build(&data).unwrap();
+            assert_eq!(index.len(), 2);
+            assert!(index.entry_range(440).is_none());
+
+            let range = index.entry_range(570).unwrap();
+            let info = index.parse_entry(570, &data[range]).unwrap();
+            assert_eq!(info.name(), Some("Dota 2"));
+            assert_eq!(info.build_id("public"), Some(12500000));
+        }
+    }
+
+    #[test]
+    fn string_table_index_out_of_range_fails() {
+        let mut data = appinfo(APPINFO_V29, APPS);
+        // Таблица в конце файла: оставляем в ней одну строку
+        let offset = i64::from_le_bytes(data[8..16].try_into().unwrap()) as usize;
+        data.truncate(offset);
+        data.extend(1u32.to_le_bytes());
+        data.extend(b"appinfo\0");
+
+        match find_appinfo(&data, 570) {
+            Err(Error::ParseError(message)) => assert!(message.contains("out of range"), "{}", message),
+            other => panic!("expected parse error, got {:?}", other.map(|info| info.map(|i| i.app_id))),
+        }
+    }
+
+    #[test]
+    fn truncated_appinfo_fails() {
+        let data = appinfo(APPINFO_V28, APPS);
+
+        assert!(find_appinfo(&data[..6], 570).is_err());
+        // Запись обрезана посередине
+        assert!(find_appinfo(&data[..data.len() - 40], 570).is_err());
+        // Нет завершающего нулевого appid
+        assert!(read_appinfo(&data[..data.len() - 4]).is_err());
+
+        let v29 = appinfo(APPINFO_V29, APPS);
+        let mut bad_offset = v29.clone();
+        bad_offset[8..16].copy_from_slice(&(v29.len() as i64 + 1).to_le_bytes());
+        assert!(find_appinfo(&bad_offset, 570).is_err());
+        assert!(find_appinfo(&v29[..v29.len() - 3], 570).is_err());
+
+        let mut unknown = data.clone();
+        unknown[..4].copy_from_slice(&0x0756_4426u32.to_le_bytes());
+        assert!(find_appinfo(&unknown, 570).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+use super::{Object, Value};
+use crate::error::{Error, Result};
+use serde::de::{
+    self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor,
+};
+use serde::{forward_to_deserialize_any, Deserialize};
+use std::collections::HashSet;
+
+pub fn from_value<'de, T: Deserialize<'de>>(value: &'de Value) -> Result<T> {
+    T::deserialize(ValueDeserializer(value))
+}
+
+pub fn from_object<'de, T: Deserialize<'de>>(object: &'de Object) -> Result<T> {
+    T::deserialize(ObjectDeserializer(object))
+}
+
+// В текстовом VDF числа и флаги хранятся строками, разбираем их по запросу
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self.as_str() {
+                    Some(s) => {
+                        let n = s.trim().parse::<$ty>().map_err(|_| {
+                            <Error as de::Error>::invalid_value(Unexpected::Str(s), &visitor)
+                        })?;
+                        visitor.$visit(n)
+                    }
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+struct ValueDeserializer<'de>(&'de Value);
+
+impl<'de> ValueDeserializer<'de> {
+    fn as_str(&self) -> Option<&'de str> {
+        self.0.as_str()
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Int32(n) => visitor.visit_i32(*n),
+            Value::UInt64(n) => visitor.visit_u64(*n),
+            Value::Int64(n) => visitor.visit_i64(*n),
+            Value::Float32(n) => visitor.visit_f32(*n),
+            Value::Pointer(n) | Value::Color(n) => visitor.visit_u32(*n),
+            Value::Object(o) => ObjectDeserializer(o).deserialize_any(visitor),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" => visitor.visit_bool(true),
+                "0" | "false" | "" => visitor.visit_bool(false),
+                _ => Err(de::Error::invalid_value(Unexpected::Str(s), &visitor)),
+            },
+            Value::Int32(n) => visitor.visit_bool(*n != 0),
+            Value::UInt64(n) => visitor.visit_bool(*n != 0),
+            Value::Int64(n) => visitor.visit_bool(*n != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Массивы в KeyValues записываются секцией с ключами "0", "1", ...
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Value::Object(o) => visitor.visit_seq(ObjectValues(o.entries().iter())),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0 {
+            Value::Object(o) => ObjectDeserializer(o).deserialize_struct(name, fields, visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0 {
+            Value::String(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            _ => Err(de::Error::invalid_type(Unexpected::Map, &visitor)),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct
+        tuple_struct map identifier ignored_any
+    }
+}
+
+struct ObjectDeserializer<'de>(&'de Object);
+
+impl<'de> de::Deserializer<'de> for ObjectDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(ObjectAccess::new(self.0, &[]))
+    }
+
+    // Поля структуры сопоставляются без учёта регистра
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_map(ObjectAccess::new(self.0, fields))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(ObjectValues(self.0.entries().iter()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple tuple_struct map
+        enum identifier ignored_any
+    }
+}
+
+struct ObjectAccess<'de> {
+    entries: std::slice::Iter<'de, (String, Value)>,
+    value: Option<&'de Value>,
+    fields: &'static [&'static str],
+    seen: HashSet<&'static str>,
+}
+
+impl<'de> ObjectAccess<'de> {
+    fn new(object: &'de Object, fields: &'static [&'static str]) -> Self {
+        Self {
+            entries: object.entries().iter(),
+            value: None,
+            fields,
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for ObjectAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        for (key, value) in self.entries.by_ref() {
+            let mut key: &'de str = key;
+            if let Some(field) = self.fields.iter().find(|f| f.eq_ignore_ascii_case(key)) {
+                // Повторяющиеся ключи допустимы в KeyValues, берём первый
+                if !self.seen.insert(field) {
+                    continue;
+                }
+                key = field;
+            }
+            self.value = Some(value);
+            return seed.deserialize(KeyDeserializer(key)).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::ParseError("VDF value requested before key".to_string()))?;
+        seed.deserialize(ValueDeserializer(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct ObjectValues<'de>(std::slice::Iter<'de, (String, Value)>);
+
+impl<'de> SeqAccess<'de> for ObjectValues<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.0.next() {
+            Some((_, value)) => seed.deserialize(ValueDeserializer(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+// Ключи секций часто числовые (appid, depot id)
+struct KeyDeserializer<'de>(&'de str);
+
+impl<'de> KeyDeserializer<'de> {
+    fn as_str(&self) -> Option<&'de str> {
+        Some(self.0)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::steam::{AppManifest, AppStateFlags, LibraryFolders};
+    use crate::vdf::{from_str, parse};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    const MANIFEST: &str = include_str!("../../tests/fixtures/steam/appmanifest_570.acf");
+    const LIBRARY_FOLDERS: &str = include_str!("../../tests/fixtures/steam/libraryfolders.vdf");
+
+    #[test]
+    fn app_manifest_is_deserialized() {
+        let manifest: AppManifest = from_str(MANIFEST).unwrap();
+
+        assert_eq!(manifest.app_id, 570);
+        assert_eq!(manifest.name, "Dota 2");
+        assert_eq!(manifest.install_dir, "dota 2 beta");
+        assert_eq!(manifest.build_id, 12500000);
+        assert_eq!(manifest.last_updated, 1700000000);
+        assert_eq!(manifest.size_on_disk, 41235678901);
+        assert_eq!(manifest.last_owner, 76561198000000000);
+        assert_eq!(AppStateFlags(manifest.state_flags), AppStateFlags::FULLY_INSTALLED);
+        assert_eq!(manifest.installed_depots.len(), 2);
+        assert_eq!(manifest.installed_depots[&381451].manifest, "7381924609182736450");
+        assert_eq!(manifest.installed_depots[&381451].size, 40000000000);
+        assert_eq!(manifest.user_config.language.as_deref(), Some("english"));
+        assert_eq!(manifest.branch(), "public");
+    }
+
+    #[test]
+    fn library_folders_are_deserialized() {
+        let library: LibraryFolders = from_str(LIBRARY_FOLDERS).unwrap();
+
+        assert_eq!(library.folders.len(), 2);
+        assert_eq!(library.folders[0].path, PathBuf::from("C:\\Program Files (x86)\\Steam"));
+        assert_eq!(library.folders[0].apps.keys().copied().collect::<Vec<_>>(), vec![228980, 250820]);
+        assert_eq!(library.folders[1].path, PathBuf::from("D:\\SteamLibrary"));
+        assert_eq!(library.folders[1].label, "Games");
+        assert_eq!(library.folders[1].total_size, 2000396742656);
+        assert_eq!(library.folders[1].apps, BTreeMap::from([(570, 41235678901)]));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        name: String,
+        enabled: bool,
+        count: u32,
+        tags: Vec<String>,
+        depots: BTreeMap<u32, String>,
+        missing: Option<String>,
+    }
+
+    #[test]
+    fn fields_ignore_case_and_take_first_duplicate() {
+        let config: Config = from_str(
+            "\"Config\"\n{\n\t\"NAME\"\t\"first\"\n\t\"name\"\t\"second\"\n\t\"Enabled\"\t\"1\"\n\t\"count\"\t\" 42 \"\n\t\"tags\"\n\t{\n\t\t\"0\"\t\"a\"\n\t\t\"1\"\t\"b\"\n\t}\n\t\"depots\"\n\t{\n\t\t\"228988\"\t\"228980\"\n\t}\n}\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.name, "first");
+        assert!(config.enabled);
+        assert_eq!(config.count, 42);
+        assert_eq!(config.tags, vec!["a", "b"]);
+        assert_eq!(config.depots, BTreeMap::from([(228988, "228980".to_string())]));
+        assert_eq!(config.missing, None);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        #[derive(Debug, Deserialize)]
+        struct Number {
+            #[allow(dead_code)]
+            value: u32,
+        }
+        #[derive(Debug, Deserialize)]
+        struct Flag {
+            #[allow(dead_code)]
+            value: bool,
+        }
+
+        assert!(from_str::<Number>("\"n\"\n{\n\t\"value\"\t\"-1\"\n}\n").is_err());
+        assert!(from_str::<Number>("\"n\"\n{\n\t\"value\"\t\"abc\"\n}\n").is_err());
+        assert!(from_str::<Flag>("\"f\"\n{\n\t\"value\"\t\"yes\"\n}\n").is_err());
+
+        let root = parse("\"root\"\n{\n}\n").unwrap();
+        assert!(from_object::<BTreeMap<String, String>>(root.get_object("root").unwrap()).unwrap().is_empty());
+        assert!(from_str::<AppManifest>("").is_err());
+    }
+}
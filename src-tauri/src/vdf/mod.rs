@@ -0,0 +1,126 @@
+mod binary;
+mod de;
+mod text;
+
+use crate::error::{Error, Result};
+use serde::Deserialize;
+
+pub use binary::{find_appinfo, parse_binary, read_appinfo, AppInfo, AppInfoIndex};
+pub use de::{from_object, from_value};
+pub use text::{parse, Parser};
+
+// Дерево KeyValues. Текстовый формат даёт только строки и вложенные секции,
+// остальные типы встречаются в бинарном формате.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Int32(i32),
+    UInt64(u64),
+    Int64(i64),
+    Float32(f32),
+    Pointer(u32),
+    Color(u32),
+    Object(Object),
+}
+
+// Секция KeyValues: сохраняет порядок и повторяющиеся ключи
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Object {
+    entries: Vec<(String, Value)>,
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Int32(n) => u64::try_from(*n).ok(),
+            Value::UInt64(n) => Some(*n),
+            Value::Int64(n) => u64::try_from(*n).ok(),
+            Value::Pointer(n) | Value::Color(n) => Some(u64::from(*n)),
+            Value::Float32(_) | Value::Object(_) => None,
+        }
+    }
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, value: Value) {
+        self.entries.push((key.into(), value));
+    }
+
+    // Ключи KeyValues регистронезависимы, возвращаем первое совпадение
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    pub fn get_object(&self, key: &str) -> Option<&Object> {
+        self.get(key).and_then(Value::as_object)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn entries(&self) -> &[(String, Value)] {
+        &self.entries
+    }
+}
+
+// Файлы Steam содержат одну корневую секцию ("AppState", "libraryfolders"),
+// десериализуем её содержимое
+pub fn from_str<T>(input: &str) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    from_root(&parse(input)?)
+}
+
+pub fn from_binary<T>(data: &[u8]) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    from_root(&parse_binary(data)?)
+}
+
+fn from_root<T>(root: &Object) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let (_, value) = root
+        .entries()
+        .first()
+        .ok_or_else(|| Error::ParseError("VDF document is empty".to_string()))?;
+    from_value(value)
+}
@@ -0,0 +1,350 @@
+use super::{Object, Value};
+use crate::error::{Error, Result};
+use std::collections::HashSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+    Condition(String),
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.trim_start_matches('\u{feff}').chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        loop {
+            let c = match self.chars.peek() {
+                Some(c) => *c,
+                None => return Ok(None),
+            };
+
+            match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                '/' => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.peek() == Some(&'/') {
+                        // Комментарий до конца строки
+                        while let Some(c) = self.chars.peek() {
+                            if *c == '\n' {
+                                break;
+                            }
+                            self.bump();
+                        }
+                    } else {
+                        return Ok(Some(Token::Str(self.read_unquoted())));
+                    }
+                }
+                '{' => {
+                    self.bump();
+                    return Ok(Some(Token::Open));
+                }
+                '}' => {
+                    self.bump();
+                    return Ok(Some(Token::Close));
+                }
+                '"' => {
+                    self.bump();
+                    return self.read_quoted().map(|s| Some(Token::Str(s)));
+                }
+                '[' => {
+                    self.bump();
+                    return self.read_condition().map(|s| Some(Token::Condition(s)));
+                }
+                _ => return Ok(Some(Token::Str(self.read_unquoted()))),
+            }
+        }
+    }
+
+    fn read_quoted(&mut self) -> Result<String> {
+        let start = self.line;
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(error(start, "unterminated string")),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('\\') => value.push('\\'),
+                    Some('"') => value.push('"'),
+                    // Неизвестные последовательности оставляем как есть
+                    Some(other) => {
+                        value.push('\\');
+                        value.push(other);
+                    }
+                    None => return Err(error(start, "unterminated string")),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn read_unquoted(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() || matches!(c, '"' | '{' | '}') {
+                break;
+            }
+            value.push(*c);
+            self.bump();
+        }
+        value
+    }
+
+    fn read_condition(&mut self) -> Result<String> {
+        let start = self.line;
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(error(start, "unterminated conditional")),
+                Some(']') => return Ok(value),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+}
+
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Token>,
+    defines: HashSet<String>,
+}
+
+pub fn parse(input: &str) -> Result<Object> {
+    Parser::new(input).parse()
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            peeked: None,
+            defines: platform_defines(),
+        }
+    }
+
+    // Переопределяет набор символов для условий вида [$WIN32]
+    pub fn with_defines<I, S>(mut self, defines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.defines = defines
+            .into_iter()
+            .map(|d| d.as_ref().trim_start_matches('$').to_ascii_uppercase())
+            .collect();
+        self
+    }
+
+    pub fn parse(mut self) -> Result<Object> {
+        self.parse_object(false)
+    }
+
+    fn next(&mut self) -> Result<Option<Token>> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    fn take_condition(&mut self) -> Result<Option<String>> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token()?;
+        }
+        match self.peeked.take() {
+            Some(Token::Condition(c)) => Ok(Some(c)),
+            other => {
+                self.peeked = other;
+                Ok(None)
+            }
+        }
+    }
+
+    fn parse_object(&mut self, nested: bool) -> Result<Object> {
+        let mut object = Object::new();
+
+        loop {
+            let key = match self.next()? {
+                Some(Token::Str(key)) => key,
+                Some(Token::Close) if nested => return Ok(object),
+                None if !nested => return Ok(object),
+                None => return Err(self.error("unexpected end of input, expected '}'")),
+                Some(Token::Close) => return Err(self.error("unexpected '}'")),
+                Some(Token::Open) => return Err(self.error("expected key, found '{'")),
+                Some(Token::Condition(c)) => {
+                    return Err(self.error(&format!("expected key, found [{}]", c)))
+                }
+            };
+
+            // Условие может стоять как перед '{', так и после значения
+            let mut condition = self.take_condition()?;
+
+            let value = match self.next()? {
+                Some(Token::Str(value)) => Value::String(value),
+                Some(Token::Open) => Value::Object(self.parse_object(true)?),
+                _ => return Err(self.error(&format!("missing value for key \"{}\"", key))),
+            };
+
+            if condition.is_none() {
+                condition = self.take_condition()?;
+            }
+
+            if condition.is_none_or(|c| self.evaluate(&c)) {
+                object.push(key, value);
+            }
+        }
+    }
+
+    // Поддерживаются !, && и || без скобок
+    fn evaluate(&self, condition: &str) -> bool {
+        condition.split("||").any(|group| {
+            group.split("&&").all(|term| {
+                let term = term.trim();
+                let (negate, name) = match term.strip_prefix('!') {
+                    Some(rest) => (true, rest.trim()),
+                    None => (false, term),
+                };
+                let name = name.trim_start_matches('$').to_ascii_uppercase();
+                self.defines.contains(&name) != negate
+            })
+        })
+    }
+
+    fn error(&self, message: &str) -> Error {
+        error(self.lexer.line, message)
+    }
+}
+
+fn error(line: usize, message: &str) -> Error {
+    Error::ParseError(format!("VDF line {}: {}", line, message))
+}
+
+fn platform_defines() -> HashSet<String> {
+    let defines: &[&str] = if cfg!(target_os = "windows") {
+        &["WINDOWS", "WIN32", "WIN64"]
+    } else if cfg!(target_os = "macos") {
+        &["OSX", "POSIX"]
+    } else {
+        &["LINUX", "POSIX"]
+    };
+    defines.iter().map(|d| d.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_with(input: &str, defines: &[&str]) -> Result<Object> {
+        Parser::new(input).with_defines(defines).parse()
+    }
+
+    #[test]
+    fn escapes_and_unquoted_tokens() {
+        let root = parse(
+            "\u{feff}\"root\"\n{\n\t\"path\"\t\"C:\\\\Games\\\\Steam\"\n\t\"quote\"\t\"say \\\"hi\\\"\"\n\t\"lines\"\t\"a\\nb\\tc\"\n\t\"unknown\"\t\"\\q\"\n\tbare\tvalue\n}\n",
+        )
+        .unwrap();
+        let root = root.get_object("root").unwrap();
+
+        assert_eq!(root.get_str("path"), Some("C:\\Games\\Steam"));
+        assert_eq!(root.get_str("quote"), Some("say \"hi\""));
+        assert_eq!(root.get_str("lines"), Some("a\nb\tc"));
+        assert_eq!(root.get_str("unknown"), Some("\\q"));
+        assert_eq!(root.get_str("bare"), Some("value"));
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let root = parse(
+            "// appinfo\n\"root\" // секция\n{\n\t\"url\"\t\"http://example.com//path\"\n\t// \"hidden\" \"1\"\n\t\"a/b\"\t\"1\"\n}\n",
+        )
+        .unwrap();
+        let root = root.get_object("root").unwrap();
+
+        assert_eq!(root.get_str("url"), Some("http://example.com//path"));
+        assert_eq!(root.get_str("a/b"), Some("1"));
+        assert_eq!(root.get("hidden"), None);
+        assert_eq!(root.len(), 2);
+    }
+
+    #[test]
+    fn conditionals_use_defines() {
+        let input = "\"root\"\n{\n\t\"exe\"\t\"game.exe\"\t[$WIN32]\n\t\"exe\"\t\"game.sh\"\t[$LINUX]\n\t\"launcher\" [!$WIN32 && $POSIX]\n\t{\n\t\t\"type\"\t\"native\"\n\t}\n\t\"any\"\t\"1\"\t[$OSX || $WIN64]\n}\n";
+
+        let linux = parse_with(input, &["LINUX", "POSIX"]).unwrap();
+        let linux = linux.get_object("root").unwrap();
+        assert_eq!(linux.get_str("exe"), Some("game.sh"));
+        assert_eq!(linux.get_object("launcher").and_then(|o| o.get_str("type")), Some("native"));
+        assert_eq!(linux.get("any"), None);
+
+        let windows = parse_with(input, &["$win32", "WIN64"]).unwrap();
+        let windows = windows.get_object("root").unwrap();
+        assert_eq!(windows.get_str("exe"), Some("game.exe"));
+        assert_eq!(windows.get("launcher"), None);
+        assert_eq!(windows.get_str("any"), Some("1"));
+    }
+
+    #[test]
+    fn duplicate_keys_are_kept_and_lookup_ignores_case() {
+        let root = parse("\"Root\"\n{\n\t\"Key\"\t\"first\"\n\t\"KEY\"\t\"second\"\n}\n").unwrap();
+        let root = root.get_object("root").unwrap();
+
+        assert_eq!(root.get_str("key"), Some("first"));
+        assert_eq!(
+            root.iter().map(|(k, v)| (k, v.as_str().unwrap())).collect::<Vec<_>>(),
+            vec![("Key", "first"), ("KEY", "second")]
+        );
+    }
+
+    fn error_message(input: &str) -> String {
+        match parse(input) {
+            Err(Error::ParseError(message)) => message,
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn malformed_input_is_reported_with_line() {
+        assert_eq!(
+            error_message("\"root\"\n{\n\t\"name\"\t\"Dota 2\n}\n"),
+            "VDF line 3: unterminated string"
+        );
+        assert_eq!(error_message("\"root\"\t\"a\\"), "VDF line 1: unterminated string");
+        assert_eq!(
+            error_message("\"root\"\t\"1\"\t[$WIN32\n"),
+            "VDF line 1: unterminated conditional"
+        );
+        assert_eq!(
+            error_message("\"root\"\n{\n\t\"a\"\t\"1\"\n"),
+            "VDF line 4: unexpected end of input, expected '}'"
+        );
+        assert_eq!(error_message("\"a\"\t\"1\"\n}\n"), "VDF line 2: unexpected '}'");
+        assert_eq!(error_message("\"root\"\n"), "VDF line 2: missing value for key \"root\"");
+        assert_eq!(error_message("{\n"), "VDF line 1: expected key, found '{'");
+    }
+}
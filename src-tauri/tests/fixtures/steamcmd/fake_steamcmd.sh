@@ -0,0 +1,51 @@
+#!/bin/sh
+# Имитация интерактивного steamcmd для тестов Steam Guard.
+# Приглашения печатаются без перевода строки, как у настоящего steamcmd.
+# Вход без сохранённого токена запрашивает пароль (верный - secret), затем код:
+# пользователь "mobile" получает запрос кода аутентификатора, остальные - кода
+# из письма. Верный код - 12345, после неверного код запрашивается повторно
+
+prompt() {
+    printf '%s' "$1"
+}
+
+echo "Redirecting stderr to 'logs/stderr.txt'"
+echo "Loading Steam API...OK"
+prompt "Steam>"
+
+while read -r command argument rest; do
+    case "$command" in
+        login)
+            echo "Logging in user '$argument' to Steam Public..."
+            prompt "password:"
+            read -r password || exit 1
+            if [ "$password" != "secret" ]; then
+                echo "FAILED (Invalid Password)"
+                prompt "Steam>"
+                continue
+            fi
+            if [ "$argument" = "mobile" ]; then
+                guard="Two-factor code:"
+            else
+                guard="Steam Guard code:"
+            fi
+            while :; do
+                prompt "$guard"
+                read -r code || exit 1
+                if [ "$code" = "12345" ]; then
+                    echo "OK"
+                    echo "Waiting for user info...OK"
+                    break
+                fi
+                echo "FAILED (Invalid Login Auth Code)"
+            done
+            ;;
+        quit)
+            exit 0
+            ;;
+        *)
+            echo "$command $argument done"
+            ;;
+    esac
+    prompt "Steam>"
+done
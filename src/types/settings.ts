@@ -6,4 +6,11 @@ export interface Settings {
   notifications: boolean;
 }
 
-export type SettingsUpdate = Partial<Settings>; 
\ No newline at end of file
+// update_settings принимает часть объекта настроек, одно изменение { key, value }
+// (ключ - поле, путь через точку или JSON pointer) или их список
+export interface SettingKeyUpdate {
+  key: string;
+  value: unknown;
+}
+
+export type SettingsUpdate = Partial<Settings> | SettingKeyUpdate | SettingKeyUpdate[]; 
\ No newline at end of file